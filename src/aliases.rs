@@ -0,0 +1,176 @@
+use std::{collections::HashMap, path::Path};
+
+/// Aliases cargo defines itself, which can be overridden in the configuration.
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("b", "build"),
+    ("c", "check"),
+    ("d", "doc"),
+    ("r", "run"),
+    ("t", "test"),
+    ("rm", "remove"),
+];
+
+/// Commands built into cargo, which can't be shadowed by aliases.
+const BUILTIN_COMMANDS: &[&str] = &[
+    "add",
+    "bench",
+    "build",
+    "check",
+    "clean",
+    "config",
+    "doc",
+    "fetch",
+    "fix",
+    "generate-lockfile",
+    "help",
+    "info",
+    "init",
+    "install",
+    "locate-project",
+    "login",
+    "logout",
+    "metadata",
+    "new",
+    "owner",
+    "package",
+    "pkgid",
+    "publish",
+    "remove",
+    "report",
+    "run",
+    "rustc",
+    "rustdoc",
+    "search",
+    "test",
+    "tree",
+    "uninstall",
+    "update",
+    "vendor",
+    "verify-project",
+    "version",
+    "yank",
+];
+
+/// Aliases from the `[alias]` tables of cargo's configuration.
+#[derive(Debug, Default)]
+pub struct Aliases {
+    aliases: HashMap<String, Vec<String>>,
+}
+
+impl Aliases {
+    /// Read the aliases configured for `dir`, i.e. in `.cargo/config.toml` of `dir` and its
+    /// ancestors, and of cargo's home. Files closer to `dir` take precedence.
+    pub fn load(dir: &Path) -> Self {
+        let mut aliases = Self::default();
+        let config_dirs = dir
+            .ancestors()
+            .map(|d| d.join(".cargo"))
+            .collect::<Vec<_>>();
+        // Cargo's home directory, where the user's global config lives
+        if let Some(home) = home::cargo_home()
+            .ok()
+            .filter(|home| !config_dirs.contains(home))
+        {
+            aliases.read_config_dir(&home);
+        }
+        for config_dir in config_dirs.iter().rev() {
+            aliases.read_config_dir(config_dir);
+        }
+        log::debug!("Configured aliases: {:?}", aliases.aliases);
+        aliases
+    }
+
+    /// Read the config file in `dir`, preferring `config` over `config.toml` like cargo does.
+    fn read_config_dir(&mut self, dir: &Path) {
+        let Some(path) = ["config", "config.toml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+        else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(config) => self.read_config(&config, &path),
+            Err(e) => log::warn!("Couldn't read cargo config {path:?}: {e}"),
+        }
+    }
+
+    /// Read the aliases of `config`, the contents of the config file at `path`.
+    fn read_config(&mut self, config: &str, path: &Path) {
+        let config = match config.parse::<toml::Value>() {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("Couldn't read cargo config {path:?}: {e}");
+                return;
+            }
+        };
+        let Some(table) = config.get("alias").and_then(toml::Value::as_table) else {
+            return;
+        };
+        for (name, value) in table {
+            let words = match value {
+                toml::Value::String(s) => s.split_whitespace().map(ToOwned::to_owned).collect(),
+                toml::Value::Array(a) => a
+                    .iter()
+                    .filter_map(|v| v.as_str().map(ToOwned::to_owned))
+                    .collect(),
+                _ => {
+                    log::warn!("Alias `{name}` in {path:?} isn't a string or an array.");
+                    continue;
+                }
+            };
+            self.aliases.insert(name.clone(), words);
+        }
+    }
+
+    /// Words `name` is an alias for, from the environment (`CARGO_ALIAS_NAME`), the config
+    /// files or cargo's builtin aliases.
+    fn get(&self, name: &str) -> Option<Vec<String>> {
+        let var = format!("CARGO_ALIAS_{}", name.to_uppercase().replace('-', "_"));
+        if let Ok(value) = std::env::var(var) {
+            return Some(value.split_whitespace().map(ToOwned::to_owned).collect());
+        }
+        if let Some(words) = self.aliases.get(name) {
+            return Some(words.clone());
+        }
+        BUILTIN_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, command)| vec![(*command).to_owned()])
+    }
+
+    /// Expand the alias `name` until it starts with a command, or return `None` if it's not
+    /// an alias.
+    pub fn expand(&self, name: &str) -> Result<Option<Vec<String>>, String> {
+        let mut expanded = vec![name.to_owned()];
+        let mut seen = vec![];
+        loop {
+            let command = &expanded[0];
+            if BUILTIN_COMMANDS.contains(&command.as_str()) {
+                break;
+            }
+            let Some(words) = self.get(command) else {
+                break;
+            };
+            if seen.contains(command) {
+                return Err(format!("Alias `{name}` has a recursive definition"));
+            }
+            if words.is_empty() {
+                return Err(format!("Alias `{command}` is empty"));
+            }
+            seen.push(command.clone());
+            expanded.splice(..1, words);
+        }
+        Ok((!seen.is_empty()).then_some(expanded))
+    }
+}
+
+#[cfg(test)]
+impl Aliases {
+    /// Aliases configured by the `config` file contents.
+    pub fn from_config(config: &str) -> Self {
+        let mut aliases = Self::default();
+        aliases.read_config(config, Path::new(".cargo/config.toml"));
+        aliases
+    }
+}
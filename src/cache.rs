@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::test_parser::ParsedSource;
+
+/// Location of the cache file, relative to the target directory.
+const CACHE_FILE: &str = "cargo-select/tests.json";
+
+/// What a cached file is compared by to find out whether it changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    /// Modification time, as seconds and nanoseconds since the Unix epoch.
+    modified: (u64, u32),
+    size: u64,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            modified: (modified.as_secs(), modified.subsec_nanos()),
+            size: metadata.len(),
+        })
+    }
+}
+
+/// What a source file and the files it includes (e.g. docs with `include_str!`) are compared by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceStamp {
+    pub file: FileStamp,
+    /// Stamps of the included files, `None` for ones that don't exist.
+    pub includes: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+impl SourceStamp {
+    /// Stamp a source file with `stamp`, from which `found` was parsed.
+    pub fn new(stamp: FileStamp, found: &ParsedSource) -> Self {
+        Self {
+            file: stamp,
+            includes: found
+                .includes
+                .iter()
+                .map(|path| (path.clone(), FileStamp::of(path)))
+                .collect(),
+        }
+    }
+
+    fn includes_unchanged(&self) -> bool {
+        self.includes
+            .iter()
+            .all(|(path, stamp)| &FileStamp::of(path) == stamp)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub stamp: SourceStamp,
+    pub found: ParsedSource,
+}
+
+/// Tests found in source files on a previous run, stored in the target directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TestCache {
+    /// Version of cargo-select that wrote the cache, as discovery may change between versions.
+    version: String,
+    /// Additional test attributes used for discovery, see `--test-attribute`.
+    test_attributes: Vec<String>,
+    /// Whether doc tests were looked for, files with only those are skipped otherwise.
+    #[serde(default)]
+    doc_tests: bool,
+    pub files: HashMap<PathBuf, CachedFile>,
+}
+
+impl TestCache {
+    pub fn new(test_attributes: &[String], doc_tests: bool) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            test_attributes: test_attributes.to_vec(),
+            doc_tests,
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn path(target_directory: &Path) -> PathBuf {
+        target_directory.join(CACHE_FILE)
+    }
+
+    /// Load the cache from `target_directory`, or get an empty one if it's missing or stale.
+    ///
+    /// A cache without `doc_tests` is stale if they are looked for now.
+    pub fn load(target_directory: &Path, test_attributes: &[String], doc_tests: bool) -> Self {
+        let path = Self::path(target_directory);
+        let cache = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice::<TestCache>(&contents),
+            Err(e) => {
+                log::debug!("No test cache at {path:?}: {e}");
+                return Self::new(test_attributes, doc_tests);
+            }
+        };
+        match cache {
+            Ok(cache)
+                if cache.version == env!("CARGO_PKG_VERSION")
+                    && cache.test_attributes == test_attributes
+                    && (cache.doc_tests || !doc_tests) =>
+            {
+                log::debug!("Loaded test cache with {} files.", cache.files.len());
+                cache
+            }
+            Ok(_) => {
+                log::info!("Test cache is stale, discarding it.");
+                Self::new(test_attributes, doc_tests)
+            }
+            Err(e) => {
+                log::warn!("Couldn't read test cache {path:?}: {e}");
+                Self::new(test_attributes, doc_tests)
+            }
+        }
+    }
+
+    /// Get what was found in `path`, if neither it (now stamped with `stamp`) nor the files it
+    /// includes have changed since.
+    pub fn get(&self, path: &Path, stamp: &FileStamp) -> Option<&CachedFile> {
+        self.files
+            .get(path)
+            .filter(|cached| &cached.stamp.file == stamp && cached.stamp.includes_unchanged())
+    }
+
+    pub fn save(&self, target_directory: &Path) -> Result<(), Box<dyn Error>> {
+        let path = Self::path(target_directory);
+        log::debug!("Saving test cache to {path:?}.");
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write to a file of our own and rename it into place, so concurrent runs never read
+        // (or write over) a half-written cache
+        let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        if let Err(e) = std::fs::rename(&temp_path, &path) {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Remove the cache from `target_directory`, returning whether there was one.
+    pub fn clear(target_directory: &Path) -> std::io::Result<bool> {
+        match std::fs::remove_file(Self::path(target_directory)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -1,15 +1,21 @@
-use std::{error::Error, path::Path};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
-use clap::{Args, Parser, Subcommand};
-use fuzzy_matcher::skim::SkimMatcherV2;
+use cargo_select::{
+    clear_cache, discover, find_manifest_dir, match_targets, near_misses, Diagnostic,
+    DiagnosticKind, DiscoveryOptions, RunTarget, SelectError, Target,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use crate::get_tests::get_tests_from_path;
+use crate::aliases::Aliases;
+use crate::commands::{self, CommandSpec, Invocation, ALL_KINDS, DEFAULT_KINDS};
+use crate::exit::{CliError, FAILURE};
+use crate::list::TargetRecord;
+use crate::shell;
 use crate::tui::Tui;
 
-use crate::select::{
-    new_complete_manifest_from_path, score_targets, targets_from_manifest, Target,
-};
-
 #[derive(Parser, Debug)]
 #[clap(bin_name = "cargo", version, author)]
 pub struct Cli {
@@ -19,7 +25,7 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    #[clap(name = "select")]
+    #[clap(name = "select", allow_hyphen_values = true)]
     SelectCommand(SelectCommand),
 }
 
@@ -29,7 +35,7 @@ pub enum Commands {
 pub struct SelectCommand {
     #[clap(
         value_parser,
-        help = "Cargo command to run with selected target (e.g. \"run\")."
+        help = "Cargo command to run with selected target (e.g. \"run\"), or \"list\" to print matching targets."
     )]
     pub cargo_command: Option<String>,
     #[clap(
@@ -37,130 +43,590 @@ pub struct SelectCommand {
         help = "Pattern to fuzzy-match targets with. Omit for interactive mode."
     )]
     pub pattern: Option<String>,
-    #[clap(value_parser, help = "Additional arguments to pass to cargo.")]
+    #[clap(
+        value_parser,
+        help = "Additional arguments to pass to cargo, and after \"--\" to the binary."
+    )]
     pub cargo_args: Vec<String>,
     #[clap(
         value_parser,
-        long = "no-skip",
-        help = "Run all tests that match selected test (i.e. dont skip names that are supersets)(tests only)"
+        long = "no-exact",
+        alias = "no-skip",
+        help = "Run all tests whose paths contain the selected test's path (i.e. don't pass `--exact`)(tests only)"
+    )]
+    pub no_exact: bool,
+    #[clap(
+        value_parser,
+        long = "test-attribute",
+        value_name = "PATH",
+        help = "Additional attribute marking test functions, e.g. \"my_crate::test\"(tests only)"
+    )]
+    pub test_attributes: Vec<String>,
+    #[clap(
+        value_parser,
+        long = "ignored",
+        conflicts_with = "include-ignored",
+        help = "Only match tests marked with `#[ignore]`(tests only)"
+    )]
+    pub ignored: bool,
+    #[clap(
+        value_parser,
+        long = "include-ignored",
+        help = "Run ignored tests along with the selected ones (i.e. pass `--include-ignored`)(tests only)"
     )]
-    pub no_skip: bool,
+    pub include_ignored: bool,
+    #[clap(
+        value_parser,
+        long = "no-cache",
+        help = "Find tests in every source file, instead of reusing ones found on previous runs(tests only)"
+    )]
+    pub no_cache: bool,
+    #[clap(
+        value_parser,
+        long = "include",
+        value_name = "GLOB",
+        help = "Only search for tests in paths matching the glob, e.g. \"crates/core/**\"(tests only)"
+    )]
+    pub include: Vec<String>,
+    #[clap(
+        value_parser,
+        long = "exclude",
+        value_name = "GLOB",
+        help = "Don't search for tests in paths matching the glob, e.g. \"**/generated/**\"(tests only)"
+    )]
+    pub exclude: Vec<String>,
+    #[clap(
+        value_parser,
+        long = "strict",
+        help = "Exit with an error if there were problems finding targets (e.g. unreadable files)"
+    )]
+    pub strict: bool,
+    #[clap(
+        value_parser,
+        long = "default-members",
+        help = "Only match targets of the workspace's default members"
+    )]
+    pub default_members: bool,
+    #[clap(
+        value_parser,
+        long = "manifest-path",
+        value_name = "PATH",
+        help = "Path to Cargo.toml, instead of the nearest one to the current directory"
+    )]
+    pub manifest_path: Option<PathBuf>,
+    #[clap(
+        value_parser,
+        long = "no-current-package-first",
+        help = "Don't rank targets of the package in the current directory above others"
+    )]
+    pub no_current_package_first: bool,
+    #[clap(
+        value_parser,
+        long = "no-auto-features",
+        help = "Don't enable features the selected target requires (i.e. don't pass `--features`)"
+    )]
+    pub no_auto_features: bool,
+    #[clap(
+        value_parser,
+        long = "dry-run",
+        help = "Print the command that would be run (with its directory), instead of running it"
+    )]
+    pub dry_run: bool,
+    #[clap(
+        value_parser,
+        long = "print-command",
+        conflicts_with = "dry-run",
+        help = "Print only the command that would be run, e.g. to capture it with `$(...)`"
+    )]
+    pub print_command: bool,
+    #[clap(
+        value_parser,
+        long = "error-format",
+        value_enum,
+        default_value = "human",
+        help = "How to print errors: `json` prints a single JSON object to stderr, for tooling"
+    )]
+    pub error_format: ErrorFormat,
+    #[clap(
+        value_parser,
+        long = "format",
+        value_enum,
+        default_value = "text",
+        help = "How to print targets: `json` prints an array, `ndjson` an object per line(list only)"
+    )]
+    pub format: ListFormat,
+    #[clap(
+        value_parser,
+        long = "template",
+        conflicts_with = "format",
+        help = "Print each target with a template, e.g. \"{kind}\\t{name}\\t{path}:{line}\". Fields: kind, package, name, path, line, column, score(list only)"
+    )]
+    pub template: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ListFormat {
+    Text,
+    Json,
+    Ndjson,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl ErrorFormat {
+    /// Print `error` to stderr.
+    pub fn print(self, error: &CliError) {
+        match self {
+            ErrorFormat::Human => {
+                eprintln!("Error: {error}");
+                if let Some(hint) = error.hint() {
+                    eprintln!("  hint: {hint}");
+                }
+            }
+            ErrorFormat::Json => eprintln!("{}", error.to_json()),
+        }
+    }
+}
+
+impl SelectCommand {
+    /// Print `targets` for `cargo select list`, or the ones matching the pattern, best first.
+    fn list_targets(&self, targets: &[Target], preferred_package: Option<&str>) {
+        let records = match &self.pattern {
+            Some(pattern) => match_targets(targets, pattern, preferred_package)
+                .into_iter()
+                .map(|m| (m.target, Some(m.score)))
+                .collect::<Vec<_>>(),
+            None => targets.iter().map(|t| (t, None)).collect(),
+        };
+        let records = records
+            .into_iter()
+            .map(|(target, score)| (target, TargetRecord::new(target, score)));
+        match (&self.template, self.format) {
+            (Some(template), _) => records.for_each(|(_, r)| println!("{}", r.format(template))),
+            (None, ListFormat::Text) => records.for_each(|(target, record)| match record.score {
+                Some(score) => println!("{score}\t{target}"),
+                None => println!("{target}"),
+            }),
+            (None, ListFormat::Json) => {
+                let records = records.map(|(_, r)| r).collect::<Vec<_>>();
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&records).expect("Targets serialize to JSON.")
+                );
+            }
+            (None, ListFormat::Ndjson) => records.for_each(|(_, record)| {
+                println!(
+                    "{}",
+                    serde_json::to_string(&record).expect("Targets serialize to JSON.")
+                )
+            }),
+        }
+    }
+
+    /// Arguments enabling the `required-features` of `target`, e.g. `--features a,b`.
+    fn feature_args(&self, target: &RunTarget) -> Vec<String> {
+        if self.no_auto_features || target.required_features.is_empty() {
+            return vec![];
+        }
+        vec!["--features".to_owned(), target.required_features.join(",")]
+    }
+}
+
+/// Print a summary of problems found during discovery, failing on any in `strict` mode.
+fn report_diagnostics(diagnostics: Vec<Diagnostic>, strict: bool) -> Result<(), SelectError> {
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+    eprintln!("Problems found while looking for targets:");
+    for diagnostic in &diagnostics {
+        eprintln!("  {diagnostic}");
+    }
+    if strict {
+        if let Some(missing) = diagnostics
+            .iter()
+            .find(|d| d.kind == DiagnosticKind::MissingMember)
+        {
+            return Err(SelectError::MissingMember {
+                member: missing.path.clone(),
+            });
+        }
+        return Err(SelectError::Problems {
+            problems: diagnostics,
+        });
+    }
+    Ok(())
+}
+
+/// Handle `cargo select cache <ACTION>`.
+fn cache_command(action: Option<&str>, manifest_path: Option<&Path>) -> Result<(), SelectError> {
+    match action {
+        Some("clear") => {
+            let manifest_dir = find_manifest_dir(manifest_path)?;
+            let workspace = discover(&manifest_dir, &[], &DiscoveryOptions::default())?;
+            let cleared = clear_cache(&workspace)?;
+            let target_directory = workspace.target_directory.unwrap_or_default();
+            if cleared {
+                println!("Cleared test cache in {}", target_directory.display());
+            } else {
+                println!("No test cache in {}", target_directory.display());
+            }
+            Ok(())
+        }
+        Some(action) => Err(SelectError::Command {
+            message: format!("Unknown cache action: {action} (expected \"clear\")"),
+        }),
+        None => Err(SelectError::Command {
+            message: "Missing cache action (expected \"clear\")".to_owned(),
+        }),
+    }
+}
+
+/// Split additional arguments into ones for cargo and ones for the test binary (after `--`).
+fn split_test_args(args: &[String]) -> (&[String], &[String]) {
+    match args.iter().position(|a| a == "--") {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, &[]),
+    }
 }
+
 impl Cli {
-    pub fn exec(mut self) -> Result<(), Box<dyn Error>> {
+    /// Parse `args`, passing the ones from the first `--` on to cargo as they are.
+    ///
+    /// Clap would drop a `--` following the pattern, which cargo needs to tell its own arguments
+    /// from the ones for the binary (e.g. `cargo select test foo -- --nocapture`).
+    pub fn parse_args(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut args = args.into_iter().collect::<Vec<_>>();
+        let trailing = match args.iter().position(|a| a == "--") {
+            Some(i) => args.split_off(i),
+            None => vec![],
+        };
+        let mut cli = Self::parse_from(args);
+        let Commands::SelectCommand(command) = &mut cli.command;
+        command
+            .cargo_args
+            .extend(trailing.iter().map(|a| a.to_string_lossy().into_owned()));
+        cli
+    }
+
+    pub fn error_format(&self) -> ErrorFormat {
+        let Commands::SelectCommand(command) = &self.command;
+        command.error_format
+    }
+
+    /// Run the command, returning the exit code of cargo.
+    pub fn exec(mut self) -> Result<i32, CliError> {
         let Commands::SelectCommand(ref mut command) = self.command;
-        let manifest = new_complete_manifest_from_path(Path::new("."))?;
-        let targets = if matches!(command.cargo_command.as_deref(), Some("t") | Some("test")) {
-            get_tests_from_path(Path::new("."))
-        } else {
-            targets_from_manifest(&manifest, Path::new("."))
+        if command.cargo_command.as_deref() == Some("cache") {
+            cache_command(command.pattern.as_deref(), command.manifest_path.as_deref())?;
+            return Ok(0);
+        }
+        let listing = command.cargo_command.as_deref() == Some("list");
+        let invocation = match command.cargo_command.as_deref() {
+            Some(c) if !listing => {
+                // Words of multi-word subcommands (e.g. `miri run`) are parsed as the pattern
+                let mut words = command
+                    .pattern
+                    .take()
+                    .into_iter()
+                    .chain(std::mem::take(&mut command.cargo_args))
+                    .collect::<Vec<_>>();
+                // Cargo reads aliases from the config of the directory it's run in
+                let dir =
+                    std::env::current_dir().map_err(|e| SelectError::io(Path::new("."), e))?;
+                let invocation = commands::resolve(c, &mut words, &Aliases::load(&dir), dir)
+                    .map_err(|message| SelectError::Command { message })?;
+                if invocation.spec.is_none() {
+                    log::warn!(
+                        "Unknown command `cargo {}`, passing targets like to `cargo build`.",
+                        invocation.words.join(" ")
+                    );
+                }
+                let mut words = words.into_iter().peekable();
+                command.pattern = words.next_if(|w| w != "--");
+                command.cargo_args = words.collect();
+                Some(invocation)
+            }
+            _ => None,
         };
+        let spec = invocation.as_ref().and_then(|i| i.spec);
+        let kinds = match spec {
+            Some(spec) => spec.kinds,
+            None if listing => ALL_KINDS,
+            None => DEFAULT_KINDS,
+        };
+        let mut options = DiscoveryOptions::default();
+        options.default_members = command.default_members;
+        options.test_attributes = std::mem::take(&mut command.test_attributes);
+        options.include = std::mem::take(&mut command.include);
+        options.exclude = std::mem::take(&mut command.exclude);
+        options.cache = !command.no_cache;
+        let manifest_dir = find_manifest_dir(command.manifest_path.as_deref())?;
+        let mut workspace = discover(&manifest_dir, kinds, &options)?;
+        let current_package = std::env::current_dir()
+            .ok()
+            .filter(|_| !command.no_current_package_first)
+            .and_then(|dir| workspace.package_of(&dir.canonicalize().unwrap_or(dir)))
+            .map(|p| p.name.clone());
+        log::debug!("Current package: {current_package:?}");
+        let mut targets = std::mem::take(&mut workspace.targets);
+        if let Some(packages) = invocation
+            .as_ref()
+            .map(|i| &i.packages)
+            .filter(|p| !p.is_empty())
+        {
+            log::info!("Only matching targets of packages selected by the alias: {packages:?}");
+            targets.retain(|t| packages.iter().any(|p| p == t.package()));
+        }
+        if command.ignored {
+            targets.retain(|t| matches!(t, Target::Test(t) if t.ignored));
+        }
+        // The interactive list shows the last targets closest to the prompt
+        targets.sort_by_key(|t| Some(t.package()) == current_package.as_deref());
+        report_diagnostics(workspace.diagnostics, command.strict)?;
+        if listing {
+            command.list_targets(&targets, current_package.as_deref());
+            return Ok(0);
+        }
+        if targets.is_empty() {
+            return Err(SelectError::NoCandidates {
+                command: invocation
+                    .as_ref()
+                    .map(|i| i.spec.map_or_else(|| i.words.join(" "), CommandSpec::name)),
+                kinds: kinds.iter().map(ToString::to_string).collect(),
+            }
+            .into());
+        }
         let selected_target = match command.pattern.take() {
-            Some(pattern) => score_targets(&targets, &pattern, &SkimMatcherV2::default())
-                .last()
-                .ok_or("No targets matched!")?,
-            None => Tui::launch(&targets)?,
+            Some(pattern) => match_targets(&targets, &pattern, current_package.as_deref())
+                .first()
+                .map(|m| m.target)
+                .ok_or_else(|| SelectError::NoTargetsMatched {
+                    near_misses: near_misses(&targets, &pattern, 3),
+                    pattern,
+                })?,
+            None => Tui::launch(&targets, current_package.as_deref())?,
         };
-        self.do_stuff_with_targets(&targets, selected_target)
+        self.do_stuff_with_targets(selected_target, invocation.as_ref(), &workspace.root)
     }
 
     fn do_stuff_with_targets(
         &self,
-        targets: &[Target],
         selected_target: &Target,
-    ) -> Result<(), Box<dyn Error>> {
+        invocation: Option<&Invocation>,
+        workspace_root: &Path,
+    ) -> Result<i32, CliError> {
         let Commands::SelectCommand(command) = &self.command;
-        match command.cargo_command.as_deref() {
-            Some("run") | Some("r") => {
-                log::info!("Selected target: {selected_target}.");
-                println!("Selected target: {selected_target}");
-                log::debug!("Creating cargo command.");
-                let mut proc_command = std::process::Command::new("cargo");
-                let (name, workspace_path) = match selected_target {
-                    Target::Bin(t) => (&t.name, &t.workspace_path),
-                    Target::Example(t) => (&t.name, &t.workspace_path),
-                    Target::Test(_) => unreachable!("You can only get tests with `test` command."),
-                };
+        let Some(invocation) = invocation else {
+            log::info!("No command provided, printing out matched target.");
+            println!("{}", selected_target);
+            return Ok(0);
+        };
+        let spec = invocation.spec;
+        let kind = selected_target.kind();
+        match spec {
+            Some(spec) if !spec.accepts(kind) => {
+                return Err(SelectError::UnsupportedTarget {
+                    command: spec.name(),
+                    kind: kind.to_string(),
+                    accepted: spec.kinds.iter().map(ToString::to_string).collect(),
+                }
+                .into());
+            }
+            _ => {}
+        }
+        // The alias selects the package itself
+        let package_args = |package: &str| {
+            if invocation.packages.is_empty() {
+                vec!["-p".to_owned(), package.to_owned()]
+            } else {
+                vec![]
+            }
+        };
+        log::info!("Selected target: {selected_target}.");
+        eprintln!("Selected target: {selected_target}");
+        log::debug!("Creating cargo command.");
+        let mut proc_command = std::process::Command::new("cargo");
+        proc_command.args(&invocation.words);
+        match &invocation.alias_dir {
+            // Aliases are looked up from the current directory, which may be outside the workspace
+            Some(dir) => proc_command
+                .current_dir(dir)
+                .arg("--manifest-path")
+                .arg(workspace_root.join("Cargo.toml")),
+            // Tests are scoped with `-p`, which only works within the workspace
+            None => proc_command.current_dir(workspace_root),
+        };
+        match selected_target {
+            Target::DocTest(test) => {
+                log::debug!("Rustdoc test: {} (line {})", test.item, test.line);
+                let (cargo_args, test_args) = split_test_args(&command.cargo_args);
                 proc_command
-                    .current_dir(&workspace_path)
-                    .arg("run")
-                    .arg(selected_target.to_cargo_flag())
-                    .arg(name)
-                    .args(&command.cargo_args);
+                    .args(package_args(&test.package))
+                    .arg("--doc")
+                    .args(cargo_args)
+                    .arg("--")
+                    .args(test.filter());
+                // Code blocks marked `ignore` are usually not meant to compile, so they are only run
+                // when asked for
+                if command.include_ignored {
+                    proc_command.arg("--include-ignored");
+                }
+                proc_command.args(test_args);
 
                 log::info!(
-                    "Spawning cargo command: {proc_command:?} in {:#?}",
-                    workspace_path
+                    "Spawning cargo command: {proc_command:?} for {:#?}",
+                    test.path
                 );
-                proc_command.spawn()?.wait()?;
             }
-            Some("t") | Some("test") => {
-                let (name, workspace_path) = match selected_target {
-                    Target::Test(t) => (&t.name, &t.path),
-                    _ => unreachable!("You can only get tests with `test` command."),
-                };
-                let to_skip = targets
-                    .iter()
-                    .filter_map(|t| {
-                        if let Target::Test(t) = t {
-                            if &t.name != name && t.name.contains(name) {
-                                Some(["--skip", &t.name])
-                            } else {
-                                None
-                            }
-                        } else {
-                            unreachable!("You can only get tests with `test` command.")
-                        }
-                    })
-                    .flatten();
-                log::info!("Selected target: {selected_target}.");
-                println!("Selected target: {selected_target}");
-                log::debug!("Creating cargo command.");
-                let mut proc_command = std::process::Command::new("cargo");
+            Target::Test(test) => {
+                log::debug!("Test attributes: {:?}", test.attributes);
+                let (cargo_args, test_args) = split_test_args(&command.cargo_args);
                 proc_command
-                    .current_dir(&workspace_path.parent().unwrap())
-                    .arg("test")
-                    .arg(name)
-                    .args(&command.cargo_args);
-
-                if !command.no_skip {
-                    proc_command.arg("--").args(to_skip);
+                    .args(package_args(&test.package))
+                    .args(test.scope.to_cargo_args())
+                    .args(cargo_args)
+                    .arg("--");
+                if test.parametrised {
+                    // Run every case generated in the test's module
+                    proc_command.arg(format!("{}::", test.name));
+                } else {
+                    if !command.no_exact {
+                        proc_command.arg("--exact");
+                    }
+                    proc_command.arg(&test.name);
+                }
+                if command.include_ignored {
+                    proc_command.arg("--include-ignored");
+                } else if test.ignored || command.ignored {
+                    proc_command.arg("--ignored");
                 }
+                proc_command.args(test_args);
 
                 log::info!(
-                    "Spawning cargo command: {proc_command:?} in {:#?}",
-                    workspace_path
+                    "Spawning cargo command: {proc_command:?} for {:#?}",
+                    test.path
                 );
-                proc_command.spawn()?.wait()?;
             }
-            Some(c) => {
-                log::info!("Selected target: {selected_target}.");
-                println!("Selected target: {selected_target}");
-                log::debug!("Creating cargo command.");
-                let mut proc_command = std::process::Command::new("cargo");
-                let (name, workspace_path) = match selected_target {
-                    Target::Bin(t) => (&t.name, &t.workspace_path),
-                    Target::Example(t) => (&t.name, &t.workspace_path),
-                    Target::Test(_) => unreachable!("You can only get tests with `test` command."),
-                };
+            _ => {
+                let t = selected_target
+                    .run_target()
+                    .expect("Every other target is run through a cargo target.");
+                if let Some(package_id) = &t.package_id {
+                    log::info!("Selected package: {package_id}.");
+                }
+                if invocation.alias_dir.is_none() {
+                    proc_command.current_dir(&t.workspace_path);
+                }
                 proc_command
-                    .current_dir(&workspace_path)
-                    .arg(c)
-                    .arg(selected_target.to_cargo_flag())
-                    .arg(name)
+                    .args(selected_target.to_target_args().unwrap_or_default())
+                    .args(package_args(&t.package))
+                    .args(command.feature_args(t))
                     .args(&command.cargo_args);
+                let runs_tests = spec.is_some_and(CommandSpec::runs_tests);
+                // Arguments for the test binary go after the user's `--`, if they gave one
+                let has_separator = command.cargo_args.iter().any(|a| a == "--");
+                match selected_target {
+                    Target::BenchFunction(f) => {
+                        if !has_separator {
+                            proc_command.arg("--");
+                        }
+                        proc_command.arg(&f.name);
+                    }
+                    Target::IntegrationTest(_) if runs_tests && command.include_ignored => {
+                        if !has_separator {
+                            proc_command.arg("--");
+                        }
+                        proc_command.arg("--include-ignored");
+                    }
+                    Target::Package(_)
+                        if spec.is_some_and(|s| s.words == ["doc"])
+                            && !command.cargo_args.iter().any(|a| a == "--open") =>
+                    {
+                        proc_command.arg("--open");
+                    }
+                    _ => {}
+                }
 
                 log::info!(
-                    "Spawning cargo command: {proc_command:?} in {:#?}",
-                    workspace_path
+                    "Spawning cargo command: {proc_command:?} for {:#?}",
+                    t.manifest_path
                 );
-                proc_command.spawn()?.wait()?;
-            }
-            None => {
-                log::info!("No command provided, printing out matched target.");
-                println!("{}", selected_target);
             }
-        };
-        Ok(())
+        }
+        if command.print_command {
+            println!("{}", shell::command_line(&proc_command));
+            return Ok(0);
+        }
+        if command.dry_run {
+            let dir = match proc_command.get_current_dir() {
+                Some(dir) => dir.to_path_buf(),
+                None => std::env::current_dir().map_err(|e| SelectError::io(Path::new("."), e))?,
+            };
+            println!(
+                "cd {} && {}",
+                shell::quote(&dir.to_string_lossy()),
+                shell::command_line(&proc_command)
+            );
+            return Ok(0);
+        }
+        let status = proc_command
+            .spawn()
+            .and_then(|mut child| child.wait())
+            .map_err(|e| SelectError::Spawn {
+                program: "cargo".to_owned(),
+                message: e.to_string(),
+            })?;
+        log::info!("Cargo exited with {status}.");
+        Ok(status_code(status))
+    }
+}
+
+/// Exit code of a finished child process, or 128 + the signal that killed it on Unix.
+fn status_code(status: std::process::ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> SelectCommand {
+        let Commands::SelectCommand(command) =
+            Cli::parse_args(args.split(' ').map(OsString::from)).command;
+        command
+    }
+
+    #[test]
+    fn separator_after_pattern_reaches_cargo() {
+        let command = parse("cargo select --dry-run test tests::asyncy -- --nocapture");
+        assert!(command.dry_run);
+        assert_eq!(command.cargo_command.as_deref(), Some("test"));
+        assert_eq!(command.pattern.as_deref(), Some("tests::asyncy"));
+        assert_eq!(command.cargo_args, ["--", "--nocapture"]);
+        assert_eq!(
+            split_test_args(&command.cargo_args),
+            (&[][..], &["--nocapture".to_owned()][..])
+        );
+    }
+
+    #[test]
+    fn cargo_flags_before_separator() {
+        let command = parse("cargo select bench bench_a --release -- --save-baseline x");
+        assert_eq!(command.pattern.as_deref(), Some("bench_a"));
+        assert_eq!(
+            command.cargo_args,
+            ["--release", "--", "--save-baseline", "x"]
+        );
     }
 }
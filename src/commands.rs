@@ -0,0 +1,409 @@
+use std::path::PathBuf;
+
+use crate::aliases::Aliases;
+use cargo_select::TargetKind::{self, *};
+
+/// Cargo subcommand cargo-select knows how to pass targets to.
+#[derive(Debug)]
+pub struct CommandSpec {
+    /// Words of the subcommand, e.g. `["miri", "run"]`.
+    pub words: &'static [&'static str],
+    /// Kinds of targets the subcommand accepts.
+    pub kinds: &'static [TargetKind],
+}
+
+impl CommandSpec {
+    pub fn accepts(&self, kind: TargetKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    /// Whether test functions are passed to the test harness, i.e. whether `--exact`,
+    /// `--ignored` and friends can be used.
+    pub fn runs_tests(&self) -> bool {
+        self.accepts(Test)
+    }
+
+    pub fn name(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
+/// Target kinds every command building cargo targets accepts.
+const BUILD_KINDS: &[TargetKind] = &[Package, Lib, Bin, Example, IntegrationTest, Bench];
+
+/// Subcommands and the targets they accept. Package targets are passed with `-p NAME`,
+/// cargo targets with their flag (e.g. `--bin NAME -p PACKAGE`), test functions and benchmark
+/// functions are passed to the harness after `--`.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        words: &["run"],
+        kinds: &[Bin, Example],
+    },
+    CommandSpec {
+        words: &["test"],
+        kinds: &[IntegrationTest, Test, DocTest],
+    },
+    CommandSpec {
+        words: &["bench"],
+        kinds: &[Bench, BenchFunction],
+    },
+    CommandSpec {
+        words: &["build"],
+        kinds: &[Lib, Bin, Example],
+    },
+    CommandSpec {
+        words: &["check"],
+        kinds: BUILD_KINDS,
+    },
+    CommandSpec {
+        words: &["clippy"],
+        kinds: BUILD_KINDS,
+    },
+    CommandSpec {
+        words: &["fix"],
+        kinds: BUILD_KINDS,
+    },
+    CommandSpec {
+        words: &["doc"],
+        kinds: &[Package],
+    },
+    // `rustc` and `rustdoc` need exactly one target to pass the extra arguments to
+    CommandSpec {
+        words: &["rustc"],
+        kinds: &[Lib, Bin, Example, IntegrationTest, Bench],
+    },
+    CommandSpec {
+        words: &["rustdoc"],
+        kinds: &[Lib, Bin, Example],
+    },
+    CommandSpec {
+        words: &["miri", "run"],
+        kinds: &[Bin, Example],
+    },
+    CommandSpec {
+        words: &["miri", "test"],
+        kinds: &[IntegrationTest, Test, DocTest],
+    },
+    CommandSpec {
+        words: &["llvm-cov", "run"],
+        kinds: &[Bin, Example],
+    },
+    // Doc tests need nightly with `llvm-cov`, leave them out
+    CommandSpec {
+        words: &["llvm-cov"],
+        kinds: &[Package, IntegrationTest, Test],
+    },
+    // nextest doesn't run doc tests
+    CommandSpec {
+        words: &["nextest", "run"],
+        kinds: &[Package, IntegrationTest, Test],
+    },
+    CommandSpec {
+        words: &["publish"],
+        kinds: &[Package],
+    },
+    CommandSpec {
+        words: &["package"],
+        kinds: &[Package],
+    },
+    CommandSpec {
+        words: &["tree"],
+        kinds: &[Package],
+    },
+];
+
+/// Every target kind, listed by `cargo select list`.
+pub const ALL_KINDS: &[TargetKind] = &[
+    Package,
+    Lib,
+    Bin,
+    Example,
+    Bench,
+    IntegrationTest,
+    BenchFunction,
+    Test,
+    DocTest,
+];
+
+/// Target kinds offered for commands not in [`COMMANDS`], passed the same way as to `cargo build`.
+pub const DEFAULT_KINDS: &[TargetKind] = BUILD_KINDS;
+
+/// Find the spec of the subcommand starting with `command`.
+///
+/// Subcommands with more words (e.g. `miri run`) take the rest from the start of `args`,
+/// which are removed from it.
+pub fn lookup(
+    command: &str,
+    args: &mut Vec<String>,
+) -> Result<Option<&'static CommandSpec>, String> {
+    let mut candidates = COMMANDS
+        .iter()
+        .filter(|c| c.words[0] == command)
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    // Prefer the longest match, e.g. `llvm-cov run` over `llvm-cov`
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.words.len()));
+    for spec in candidates.iter().copied() {
+        let rest = &spec.words[1..];
+        if args.len() >= rest.len() && args.iter().zip(rest).all(|(a, w)| a == w) {
+            args.drain(..rest.len());
+            return Ok(Some(spec));
+        }
+    }
+    let subcommands = candidates
+        .iter()
+        .map(|c| c.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(format!(
+        "Unsupported `cargo {command}` subcommand (supported: {subcommands})"
+    ))
+}
+
+/// Cargo command to run with the selected target.
+#[derive(Debug)]
+pub struct Invocation {
+    /// Words passed to cargo before the target, e.g. `["miri", "run"]`, an alias, or what the
+    /// alias expands to if it selects the whole workspace.
+    pub words: Vec<String>,
+    /// Spec of the subcommand, after expanding aliases.
+    pub spec: Option<&'static CommandSpec>,
+    /// Packages the alias selects with `-p`, which restrict the targets offered.
+    pub packages: Vec<String>,
+    /// Directory the alias was configured for, cargo has to be run there to find it.
+    pub alias_dir: Option<PathBuf>,
+}
+
+/// Resolve `command`, expanding it if it's one of `aliases` configured for `dir`.
+///
+/// Words of multi-word subcommands are taken from the start of `args`, like with [`lookup`].
+pub fn resolve(
+    command: &str,
+    args: &mut Vec<String>,
+    aliases: &Aliases,
+    dir: PathBuf,
+) -> Result<Invocation, String> {
+    let Some(expanded) = aliases.expand(command)? else {
+        let spec = lookup(command, args)?;
+        let words = match spec {
+            Some(spec) => spec.words.iter().map(ToString::to_string).collect(),
+            None => vec![command.to_owned()],
+        };
+        return Ok(Invocation {
+            words,
+            spec,
+            packages: vec![],
+            alias_dir: None,
+        });
+    };
+    log::info!("Expanded alias `{command}` to `{}`.", expanded.join(" "));
+    let (subcommand, alias_args) = expanded
+        .split_first()
+        .expect("Expanded aliases aren't empty.");
+    let user_args = std::mem::take(args);
+    let mut rest = alias_args
+        .iter()
+        .chain(&user_args)
+        .cloned()
+        .collect::<Vec<_>>();
+    let spec = lookup(subcommand, &mut rest)?;
+    let taken = alias_args.len() + user_args.len() - rest.len();
+    // Words of the subcommand missing from the alias are passed along with it
+    let taken_from_user = taken.saturating_sub(alias_args.len());
+    let (mut words, packages) = match without_workspace_args(&expanded) {
+        // Cargo ignores `-p` along with `--workspace`, so the expanded command is run without it
+        Some(scoped) => {
+            log::info!(
+                "Running `{}` instead of alias `{command}`, to scope it to the selected target.",
+                scoped.join(" ")
+            );
+            let packages = package_args(&scoped[1..]);
+            (scoped, packages)
+        }
+        None => (vec![command.to_owned()], package_args(alias_args)),
+    };
+    words.extend_from_slice(&user_args[..taken_from_user]);
+    args.extend_from_slice(&user_args[taken_from_user..]);
+    Ok(Invocation {
+        words,
+        spec,
+        packages,
+        alias_dir: Some(dir),
+    })
+}
+
+/// `args` without the ones selecting the whole workspace (`--workspace`, `--all` and
+/// `--exclude`), or `None` if there are none.
+fn without_workspace_args(args: &[String]) -> Option<Vec<String>> {
+    let len = args.len();
+    let mut kept = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                kept.push(arg.clone());
+                kept.extend(args.by_ref().cloned());
+            }
+            "--workspace" | "--all" => {}
+            "--exclude" => {
+                args.next();
+            }
+            _ if arg.starts_with("--exclude=") => {}
+            _ => kept.push(arg.clone()),
+        }
+    }
+    (kept.len() < len).then_some(kept)
+}
+
+/// Packages selected in `args` with `-p`/`--package`.
+fn package_args(args: &[String]) -> Vec<String> {
+    let mut packages = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => break,
+            "-p" | "--package" => packages.extend(args.next().cloned()),
+            _ => {
+                if let Some(package) = arg
+                    .strip_prefix("--package=")
+                    .or_else(|| arg.strip_prefix("-p"))
+                {
+                    packages.push(package.to_owned());
+                }
+            }
+        }
+    }
+    packages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(ToString::to_string).collect()
+    }
+
+    fn resolve_alias(config: &str, command: &str, args: &[&str]) -> (Invocation, Vec<String>) {
+        let mut args = words(args);
+        let invocation = resolve(
+            command,
+            &mut args,
+            &Aliases::from_config(config),
+            PathBuf::from("."),
+        )
+        .expect("Command resolves.");
+        (invocation, args)
+    }
+
+    #[test]
+    fn multi_word_subcommand_completed_by_user_args() {
+        let (invocation, args) = resolve_alias(
+            "[alias]\nmr = \"miri\"",
+            "mr",
+            &["run", "pattern", "--release"],
+        );
+        assert_eq!(invocation.words, ["mr", "run"]);
+        assert_eq!(
+            invocation.spec.map(CommandSpec::name).as_deref(),
+            Some("miri run")
+        );
+        assert!(invocation.packages.is_empty());
+        assert_eq!(args, ["pattern", "--release"]);
+    }
+
+    #[test]
+    fn multi_word_subcommand_in_alias() {
+        let (invocation, args) = resolve_alias(
+            "[alias]\nmt = [\"miri\", \"test\", \"--release\"]",
+            "mt",
+            &["run", "pattern"],
+        );
+        assert_eq!(invocation.words, ["mt"]);
+        assert_eq!(
+            invocation.spec.map(CommandSpec::name).as_deref(),
+            Some("miri test")
+        );
+        assert_eq!(args, ["run", "pattern"]);
+    }
+
+    #[test]
+    fn alias_selecting_packages() {
+        let (invocation, args) = resolve_alias(
+            "[alias]\nxr = \"run --release -p tools\"",
+            "xr",
+            &["server"],
+        );
+        assert_eq!(invocation.words, ["xr"]);
+        assert_eq!(
+            invocation.spec.map(CommandSpec::name).as_deref(),
+            Some("run")
+        );
+        assert_eq!(invocation.packages, ["tools"]);
+        assert_eq!(args, ["server"]);
+    }
+
+    #[test]
+    fn alias_selecting_workspace() {
+        let (invocation, args) = resolve_alias(
+            "[alias]\ntw = \"test --workspace --exclude tools --release\"",
+            "tw",
+            &["asyncy", "--", "--nocapture"],
+        );
+        assert_eq!(invocation.words, ["test", "--release"]);
+        assert_eq!(
+            invocation.spec.map(CommandSpec::name).as_deref(),
+            Some("test")
+        );
+        assert!(invocation.packages.is_empty());
+        assert_eq!(args, ["asyncy", "--", "--nocapture"]);
+    }
+
+    #[test]
+    fn alias_of_alias() {
+        let (invocation, _) = resolve_alias(
+            "[alias]\nxt = \"tt --release\"\ntt = \"t --all-features\"",
+            "xt",
+            &[],
+        );
+        assert_eq!(invocation.words, ["xt"]);
+        assert_eq!(
+            invocation.spec.map(CommandSpec::name).as_deref(),
+            Some("test")
+        );
+    }
+
+    #[test]
+    fn recursive_alias() {
+        let aliases = Aliases::from_config("[alias]\nloop-a = \"loop-b\"\nloop-b = \"loop-a -v\"");
+        let error = resolve("loop-a", &mut vec![], &aliases, PathBuf::from("."))
+            .expect_err("Recursive aliases don't resolve.");
+        assert!(error.contains("recursive"), "{error}");
+    }
+
+    #[test]
+    fn builtin_commands_are_not_aliased() {
+        let (invocation, _) = resolve_alias("[alias]\ntest = \"run\"", "test", &[]);
+        assert_eq!(invocation.words, ["test"]);
+        assert_eq!(invocation.alias_dir, None);
+    }
+
+    #[test]
+    fn package_arg_forms() {
+        let args = words(&[
+            "-p",
+            "a",
+            "-pb",
+            "--package",
+            "c",
+            "--package=d",
+            "--release",
+            "--",
+            "-p",
+            "e",
+        ]);
+        assert_eq!(package_args(&args), ["a", "b", "c", "d"]);
+    }
+}
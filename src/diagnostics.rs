@@ -0,0 +1,78 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    /// A workspace member listed in the manifest doesn't exist.
+    MissingMember,
+    Other,
+}
+
+/// Problem with a file or workspace member that discovery skipped over.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub message: String,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn new(path: &Path, message: impl Into<String>) -> Self {
+        Self::with_kind(path, message, DiagnosticKind::Other)
+    }
+
+    pub fn missing_member(path: &Path) -> Self {
+        Self::with_kind(
+            path,
+            "workspace member has no Cargo.toml",
+            DiagnosticKind::MissingMember,
+        )
+    }
+
+    fn with_kind(path: &Path, message: impl Into<String>, kind: DiagnosticKind) -> Self {
+        let diagnostic = Self {
+            path: path.to_path_buf(),
+            message: message.into(),
+            kind,
+        };
+        log::debug!("Diagnostic: {diagnostic}");
+        diagnostic
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.to_string_lossy(), self.message)
+    }
+}
+
+/// Diagnostics collected during discovery, possibly from multiple threads.
+#[derive(Debug, Default)]
+pub struct Diagnostics(Mutex<Vec<Diagnostic>>);
+
+impl Diagnostics {
+    pub fn push(&self, diagnostic: Diagnostic) {
+        self.0
+            .lock()
+            .expect("Diagnostics lock poisoned.")
+            .push(diagnostic);
+    }
+
+    pub fn extend(&self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        self.0
+            .lock()
+            .expect("Diagnostics lock poisoned.")
+            .extend(diagnostics);
+    }
+
+    pub fn into_inner(self) -> Vec<Diagnostic> {
+        self.0.into_inner().expect("Diagnostics lock poisoned.")
+    }
+}
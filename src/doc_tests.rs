@@ -0,0 +1,250 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use syn::{Attribute, Expr, ExprLit, Lit, LitStr, Meta};
+
+/// Code block in documentation that rustdoc runs as a test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoundDocTest {
+    /// Path of the documented item relative to the file's module, empty for the module itself.
+    pub item: Vec<String>,
+    /// File the code block is in, if it's not the parsed one (e.g. with `include_str!`).
+    pub file: Option<PathBuf>,
+    /// Line of the code block in its file.
+    pub line: usize,
+    pub ignore: bool,
+    pub no_run: bool,
+    pub compile_fail: bool,
+}
+
+/// Get the file included by a `#[doc = include_str!(...)]` attribute, relative to `dir`.
+pub fn included_file(attr: &Attribute, dir: &Path) -> Option<PathBuf> {
+    let Meta::NameValue(doc) = &attr.meta else {
+        return None;
+    };
+    match &doc.value {
+        Expr::Macro(mac) if attr.path().is_ident("doc") && mac.mac.path.is_ident("include_str") => {
+            let included = mac.mac.parse_body::<LitStr>().ok()?;
+            Some(dir.join(included.value()))
+        }
+        _ => None,
+    }
+}
+
+/// Line of documentation, along with where it comes from.
+struct DocLine {
+    text: String,
+    file: Option<PathBuf>,
+    line: usize,
+}
+
+/// Get lines of documentation from `#[doc]` attributes (including `///` and `//!` comments).
+///
+/// `dir` is the directory of the parsed file, used to resolve `#[doc = include_str!(...)]`.
+fn doc_lines(attrs: &[Attribute], dir: &Path) -> Vec<DocLine> {
+    let mut lines = vec![];
+    for attr in attrs.iter().filter(|a| a.path().is_ident("doc")) {
+        let Meta::NameValue(doc) = &attr.meta else {
+            continue;
+        };
+        match &doc.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(text),
+                ..
+            }) => {
+                let start = text.span().start().line;
+                lines.extend(text.value().lines().enumerate().map(|(i, text)| DocLine {
+                    text: text.to_owned(),
+                    file: None,
+                    line: start + i,
+                }));
+            }
+            Expr::Macro(_) => {
+                let Some(file) = included_file(attr, dir) else {
+                    continue;
+                };
+                let contents = match std::fs::read_to_string(&file) {
+                    Ok(contents) => contents,
+                    Err(e) => {
+                        log::warn!("Couldn't read included docs {file:?}: {e}");
+                        continue;
+                    }
+                };
+                lines.extend(contents.lines().enumerate().map(|(i, text)| DocLine {
+                    text: text.to_owned(),
+                    file: Some(file.clone()),
+                    line: i + 1,
+                }));
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Markers of a code block relevant for running it, parsed from its info string.
+struct LangString {
+    ignore: bool,
+    no_run: bool,
+    compile_fail: bool,
+}
+
+impl LangString {
+    /// Parse the info string of a code block, or `None` if it's not Rust code
+    /// (e.g. ```` ```text ````).
+    fn parse(info: &str) -> Option<Self> {
+        let mut lang = LangString {
+            ignore: false,
+            no_run: false,
+            compile_fail: false,
+        };
+        let mut seen_rust = false;
+        let mut seen_other = false;
+        for token in info.split([',', ' ', '\t']).filter(|t| !t.is_empty()) {
+            match token {
+                "rust" => seen_rust = true,
+                "ignore" => lang.ignore = true,
+                "no_run" => lang.no_run = true,
+                "compile_fail" => lang.compile_fail = true,
+                "should_panic" | "test_harness" | "standalone_crate" | "allow_fail" => {}
+                t if t.starts_with("ignore-") => lang.ignore = true,
+                t if t.starts_with("edition") => {}
+                _ => seen_other = true,
+            }
+        }
+        (seen_rust || !seen_other).then_some(lang)
+    }
+}
+
+/// Get the fence (e.g. ```` ``` ```` or `~~~`) opening a code block on `line`.
+fn fence(line: &str) -> Option<&str> {
+    let marker = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.chars().take_while(|&c| c == marker).count();
+    (len >= 3).then_some(&line[..len])
+}
+
+/// Get doc tests from the documentation of `item`.
+pub fn doc_tests(attrs: &[Attribute], item: &[String], dir: &Path) -> Vec<FoundDocTest> {
+    let mut tests = vec![];
+    let mut open_fence: Option<String> = None;
+    for line in doc_lines(attrs, dir) {
+        let text = line.text.trim_start();
+        match &open_fence {
+            Some(marker) => {
+                if text.starts_with(marker.as_str()) {
+                    open_fence = None;
+                }
+            }
+            None => {
+                let Some(marker) = fence(text) else {
+                    continue;
+                };
+                open_fence = Some(marker.to_owned());
+                let Some(lang) = LangString::parse(&text[marker.len()..]) else {
+                    continue;
+                };
+                log::trace!("Found doc test: {item:?} (line {})", line.line);
+                tests.push(FoundDocTest {
+                    item: item.to_vec(),
+                    file: line.file,
+                    line: line.line,
+                    ignore: lang.ignore,
+                    no_run: lang.no_run,
+                    compile_fail: lang.compile_fail,
+                });
+            }
+        }
+    }
+    tests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    fn markers(info: &str) -> Option<(bool, bool, bool)> {
+        LangString::parse(info).map(|l| (l.ignore, l.no_run, l.compile_fail))
+    }
+
+    fn function_doc_tests(source: &str, dir: &Path) -> Vec<FoundDocTest> {
+        let function = syn::parse_str::<syn::ItemFn>(source).expect("Source parses.");
+        doc_tests(&function.attrs, &["foo".to_owned()], dir)
+    }
+
+    #[test]
+    fn lang_string_markers() {
+        assert_eq!(markers(""), Some((false, false, false)));
+        assert_eq!(markers("rust"), Some((false, false, false)));
+        assert_eq!(markers("ignore"), Some((true, false, false)));
+        assert_eq!(markers("ignore-windows"), Some((true, false, false)));
+        assert_eq!(markers("no_run"), Some((false, true, false)));
+        assert_eq!(markers("rust,compile_fail"), Some((false, false, true)));
+        assert_eq!(
+            markers("should_panic, edition2021"),
+            Some((false, false, false))
+        );
+        assert_eq!(markers("text"), None);
+        assert_eq!(markers("sh"), None);
+        assert_eq!(markers("rust,text"), Some((false, false, false)));
+    }
+
+    #[test]
+    fn fences() {
+        let source = r#"
+/// Some docs.
+///
+/// ```
+/// foo();
+/// ```
+///
+/// ```text
+/// not a test
+/// ```
+///
+/// ~~~no_run
+/// ```
+/// still in the block
+/// ```
+/// ~~~
+///
+///   ````compile_fail
+///   ```
+///   ````
+fn foo() {}
+"#;
+        let tests = function_doc_tests(source, Path::new("."));
+        let found = tests
+            .iter()
+            .map(|t| (t.line, t.ignore, t.no_run, t.compile_fail))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (4, false, false, false),
+                (12, false, true, false),
+                (18, false, false, true)
+            ]
+        );
+        assert!(tests.iter().all(|t| t.item == ["foo"] && t.file.is_none()));
+    }
+
+    #[test]
+    fn included_docs() {
+        let dir = temp_dir("docs", &[("DOC.md", "# Foo\n\n```ignore\nfoo();\n```\n")]);
+        let source = "/// ```\n/// foo();\n/// ```\n#[doc = include_str!(\"DOC.md\")]\nfn foo() {}";
+        let tests = function_doc_tests(source, &dir);
+        let found = tests
+            .iter()
+            .map(|t| (t.file.clone(), t.line, t.ignore))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [(None, 1, false), (Some(dir.join("DOC.md")), 3, true)]
+        );
+        let missing =
+            function_doc_tests("#[doc = include_str!(\"MISSING.md\")]\nfn foo() {}", &dir);
+        assert!(missing.is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
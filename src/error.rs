@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::diagnostics::Diagnostic;
+
+/// Reason cargo-select stopped before running cargo.
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SelectError {
+    /// No `Cargo.toml` at the given path, or in the directory or its parents.
+    ManifestNotFound {
+        path: PathBuf,
+    },
+    ManifestParse {
+        path: PathBuf,
+        message: String,
+    },
+    MissingMember {
+        member: PathBuf,
+    },
+    /// Problems found during discovery, with `--strict`.
+    Problems {
+        problems: Vec<Diagnostic>,
+    },
+    /// Discovery failed for another reason, e.g. an unreadable directory.
+    Discovery {
+        message: String,
+    },
+    /// Nothing could be offered to the command, before matching anything.
+    NoCandidates {
+        command: Option<String>,
+        kinds: Vec<String>,
+    },
+    NoTargetsMatched {
+        pattern: String,
+        /// Names of the candidates closest to the pattern.
+        near_misses: Vec<String>,
+    },
+    /// The selected target can't be passed to the command.
+    UnsupportedTarget {
+        command: String,
+        kind: String,
+        accepted: Vec<String>,
+    },
+    /// The command couldn't be resolved, e.g. an unsupported subcommand or a broken alias.
+    Command {
+        message: String,
+    },
+    Spawn {
+        program: String,
+        message: String,
+    },
+    /// A file or directory couldn't be accessed.
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl SelectError {
+    pub fn io(path: &Path, error: std::io::Error) -> Self {
+        Self::Io {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }
+    }
+
+    /// What the user could do about the error.
+    pub fn hint(&self) -> Option<String> {
+        Some(match self {
+            SelectError::ManifestNotFound { .. } => {
+                "run `cargo select` inside a Cargo project, or point `--manifest-path` at a Cargo.toml"
+                    .to_owned()
+            }
+            SelectError::ManifestParse { path, .. } => format!(
+                "fix the manifest, `cargo metadata --manifest-path {}` shows what Cargo thinks of it",
+                path.display()
+            ),
+            SelectError::MissingMember { .. } => {
+                "create the member, or remove it from `workspace.members` (or add it to `workspace.exclude`)"
+                    .to_owned()
+            }
+            SelectError::Problems { .. } => {
+                "fix the problems listed above, or run without `--strict` to skip over them".to_owned()
+            }
+            SelectError::Discovery { .. } => return None,
+            SelectError::NoCandidates { .. } => {
+                "check the target kinds the command accepts, and options narrowing the search (`--default-members`, `--include`, `--exclude`, `--ignored`)"
+                    .to_owned()
+            }
+            SelectError::NoTargetsMatched { near_misses, .. } => match near_misses.as_slice() {
+                [] => "try a shorter pattern, or leave it out to pick a target interactively"
+                    .to_owned(),
+                [near_miss] => format!("did you mean `{near_miss}`?"),
+                near_misses => format!("did you mean one of: {}?", near_misses.join(", ")),
+            },
+            SelectError::UnsupportedTarget { accepted, .. } => {
+                format!("pick one of: {}", accepted.join(", "))
+            }
+            SelectError::Command { .. } => return None,
+            SelectError::Spawn { program, .. } => format!("make sure `{program}` is in your PATH"),
+            SelectError::Io { .. } => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for SelectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectError::ManifestNotFound { path } => {
+                write!(f, "Couldn't find Cargo.toml at {}", path.display())
+            }
+            SelectError::ManifestParse { path, message } => {
+                write!(f, "Couldn't read manifest {}: {message}", path.display())
+            }
+            SelectError::MissingMember { member } => {
+                write!(f, "Workspace member {} doesn't exist", member.display())
+            }
+            SelectError::Problems { problems } => {
+                write!(f, "Found {} problems (--strict)", problems.len())
+            }
+            SelectError::Discovery { message } => {
+                write!(f, "Couldn't look for targets: {message}")
+            }
+            SelectError::NoCandidates {
+                command: Some(command),
+                kinds,
+            } => write!(
+                f,
+                "No targets for `cargo {command}` (it accepts: {})",
+                kinds.join(", ")
+            ),
+            SelectError::NoCandidates { command: None, .. } => write!(f, "No targets found"),
+            SelectError::NoTargetsMatched { pattern, .. } => {
+                write!(f, "No targets matched `{pattern}`")
+            }
+            SelectError::UnsupportedTarget { command, kind, .. } => {
+                write!(f, "`cargo {command}` can't be run for a {kind}")
+            }
+            SelectError::Command { message } => write!(f, "{message}"),
+            SelectError::Io { path, message } => {
+                write!(f, "Couldn't access {}: {message}", path.display())
+            }
+            SelectError::Spawn { program, message } => {
+                write!(f, "Couldn't run `{program}`: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelectError {}
@@ -0,0 +1,92 @@
+use cargo_select::SelectError;
+use serde::Serialize;
+
+/// Exit code when no target matched the pattern.
+pub const NO_MATCH: i32 = 3;
+/// Exit code when the user closed the interactive mode without selecting a target.
+pub const ABORTED: i32 = 4;
+/// Exit code when the workspace or its targets couldn't be discovered.
+pub const DISCOVERY_ERROR: i32 = 5;
+/// Exit code for any other error.
+pub const FAILURE: i32 = 1;
+
+/// Reason `cargo select` stopped before running cargo: an error of the library, or of the
+/// interactive mode.
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum CliError {
+    Aborted,
+    /// The terminal couldn't be set up or used for interactive mode.
+    Terminal {
+        message: String,
+    },
+    #[serde(untagged)]
+    Select(SelectError),
+}
+
+impl CliError {
+    pub fn terminal(error: std::io::Error) -> Self {
+        Self::Terminal {
+            message: error.to_string(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Aborted => ABORTED,
+            CliError::Terminal { .. } => FAILURE,
+            CliError::Select(
+                SelectError::NoCandidates { .. } | SelectError::NoTargetsMatched { .. },
+            ) => NO_MATCH,
+            CliError::Select(
+                SelectError::ManifestNotFound { .. }
+                | SelectError::ManifestParse { .. }
+                | SelectError::MissingMember { .. }
+                | SelectError::Problems { .. }
+                | SelectError::Discovery { .. },
+            ) => DISCOVERY_ERROR,
+            CliError::Select(_) => FAILURE,
+        }
+    }
+
+    /// What the user could do about the error.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            CliError::Aborted => None,
+            CliError::Terminal { .. } => Some(
+                "interactive mode needs a terminal, pass a pattern to select a target without it"
+                    .to_owned(),
+            ),
+            CliError::Select(error) => error.hint(),
+        }
+    }
+
+    /// The error as a JSON object, with its kind (`error`), fields, `message`, `hint` and `exit_code`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).expect("Errors serialize to JSON.");
+        if let Some(object) = value.as_object_mut() {
+            object.insert("message".to_owned(), self.to_string().into());
+            object.insert("hint".to_owned(), self.hint().into());
+            object.insert("exit_code".to_owned(), self.exit_code().into());
+        }
+        value
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Aborted => write!(f, "User interrupt."),
+            CliError::Terminal { message } => write!(f, "Couldn't use the terminal: {message}"),
+            CliError::Select(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<SelectError> for CliError {
+    fn from(error: SelectError) -> Self {
+        Self::Select(error)
+    }
+}
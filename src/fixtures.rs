@@ -0,0 +1,17 @@
+//! Files written to temporary directories, for tests that need them on disk.
+
+use std::path::PathBuf;
+
+/// Write `files` (relative paths and contents) into a new temporary directory named after
+/// `name`, returning its canonical path.
+pub fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cargo-select-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir.canonicalize().unwrap()
+}
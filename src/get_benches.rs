@@ -0,0 +1,90 @@
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::select::{BenchFunctionTarget, Target};
+
+/// Get the contents of every `criterion_group!` invocation in `source`.
+fn criterion_groups(source: &str) -> Vec<&str> {
+    let mut groups = vec![];
+    let mut rest = source;
+    while let Some(i) = rest.find("criterion_group!") {
+        rest = &rest[i + "criterion_group!".len()..];
+        let Some(start) = rest.find(['(', '{', '[']) else {
+            break;
+        };
+        let mut depth = 0;
+        let mut end = None;
+        for (i, c) in rest.char_indices().skip(start) {
+            match c {
+                '(' | '{' | '[' => depth += 1,
+                ')' | '}' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(end) = end else {
+            break;
+        };
+        groups.push(&rest[start + 1..end]);
+        rest = &rest[end..];
+    }
+    groups
+}
+
+/// Get names of the functions registered in a `criterion_group!` invocation.
+///
+/// Handles both the short form (`criterion_group!(benches, a, b)`)
+/// and the long form (`criterion_group!{ name = benches; config = ...; targets = a, b }`).
+fn group_functions(group: &str) -> Vec<String> {
+    let functions = match group
+        .split(';')
+        .find_map(|s| s.trim().strip_prefix("targets"))
+        .and_then(|s| s.trim_start().strip_prefix('='))
+    {
+        Some(targets) => targets.split(',').collect::<Vec<_>>(),
+        None => group.split(',').skip(1).collect(),
+    };
+    functions
+        .into_iter()
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Get criterion benchmark functions from sources of given bench targets.
+///
+/// Sources that can't be read are added to `diagnostics`.
+pub fn get_bench_functions(benches: &[Target], diagnostics: &Diagnostics) -> Vec<Target> {
+    let mut ret = vec![];
+    for bench in benches.iter().filter_map(|t| match t {
+        Target::Bench(t) => Some(t),
+        _ => None,
+    }) {
+        log::debug!("Getting bench functions from: {:?}", bench.path);
+        let source = match std::fs::read_to_string(&bench.path) {
+            Ok(source) => source,
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(
+                    &bench.path,
+                    format!("couldn't read bench: {e}"),
+                ));
+                continue;
+            }
+        };
+        for name in criterion_groups(&source)
+            .into_iter()
+            .flat_map(group_functions)
+        {
+            log::trace!("Found bench function: {name}");
+            ret.push(Target::BenchFunction(BenchFunctionTarget {
+                name,
+                bench: bench.clone(),
+            }));
+        }
+    }
+    ret
+}
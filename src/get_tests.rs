@@ -1,84 +1,590 @@
-use std::{
-    io::{BufRead, BufReader},
-    path::Path,
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use ignore::{overrides::OverrideBuilder, DirEntry, WalkBuilder};
+use memchr::memmem::Finder;
+
+use crate::cache::{CachedFile, FileStamp, SourceStamp, TestCache};
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::select::{DocTestTarget, Target, TestScope, TestTarget, Workspace};
+use crate::test_parser::{
+    parse_source, scan_modules, scan_source, ParsedSource, KNOWN_TEST_ATTRIBUTES,
 };
 
-use walkdir::{DirEntry, WalkDir};
-
-use crate::select::{Target, TestTarget};
-
-fn is_rust_source_or_dir(dir_entry: &DirEntry) -> bool {
-    log::trace!("is_rust_source_or_dir {dir_entry:?}");
-    dir_entry
-        .file_name()
-        .to_str()
-        .map(|s| {
-            s.ends_with(".rs")
-                || (dir_entry.file_type().is_dir()
-                    && dir_entry
-                        .file_name()
-                        .to_str()
-                        .map(|s| s != "target")
-                        .unwrap_or(true))
-        })
-        .unwrap_or(false)
+/// Whether the walk should descend into the directory at `dir`.
+///
+/// Skips target directories (including nested ones, marked with `CACHEDIR.TAG`)
+/// and directories that aren't inside (or on the way to) a workspace member.
+fn is_walkable_dir(dir: &Path, package_dirs: &[PathBuf]) -> bool {
+    if dir.file_name().is_some_and(|name| name == "target") || dir.join("CACHEDIR.TAG").exists() {
+        log::debug!("Skipping target directory {dir:?}.");
+        return false;
+    }
+    let in_workspace = package_dirs
+        .iter()
+        .any(|p| dir.starts_with(p) || p.starts_with(dir));
+    if !in_workspace {
+        log::debug!("Skipping {dir:?}, it's not in any workspace member.");
+    }
+    in_workspace
 }
 
-fn get_tests_from_file(dir_entry: &DirEntry) -> Vec<Target> {
-    log::debug!("Getting tests from file: {dir_entry:?}");
-    assert!(dir_entry.file_type().is_file());
-    let path = dir_entry.path().to_path_buf();
-    let mut tests = vec![];
-    let file = std::fs::File::open(dir_entry.path()).expect("Couldn't open file lol");
-    let mut find_test_function = false;
-    for line in BufReader::new(file).lines() {
-        let line = line.unwrap();
-        let line = line.trim();
-        if line == "#[test]" {
-            find_test_function = true;
-        } else if find_test_function {
-            //TODO: this still can miss things like:
-            // ```
-            // #[test]
-            // fn
-            // foo
-            // ()
-            // {}
-            // ```
-            if let Some(line) = line
-                .strip_prefix("fn")
-                .or_else(|| line.strip_prefix("pub fn"))
-            {
-                // get just the name of the function
-                let i = line
-                    .find('(')
-                    .unwrap_or_else(|| line.len().saturating_sub(1));
-                let name = line[..i].trim().to_owned();
-
-                log::trace!("Found test: {name}");
-
-                tests.push(Target::Test(TestTarget {
-                    name,
-                    path: path.clone(),
-                }));
-
-                find_test_function = false;
+/// Options for discovering targets.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct DiscoveryOptions {
+    /// Whether to only discover targets of the workspace's default members.
+    pub default_members: bool,
+    /// Paths of attributes marking test functions, in addition to the known ones.
+    pub test_attributes: Vec<String>,
+    /// Globs of paths to search for tests (relative to the searched directory), all if empty.
+    pub include: Vec<String>,
+    /// Globs of paths not to search for tests.
+    pub exclude: Vec<String>,
+    /// Whether to reuse tests found on previous runs in files that haven't changed since.
+    pub cache: bool,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        Self {
+            default_members: false,
+            test_attributes: vec![],
+            include: vec![],
+            exclude: vec![],
+            cache: true,
+        }
+    }
+}
+
+/// Cheap check whether a source file may contain tests, done before parsing it.
+struct PreFilter {
+    needles: Vec<Finder<'static>>,
+    module: Finder<'static>,
+}
+
+impl PreFilter {
+    fn new(options: &DiscoveryOptions, doc_tests: bool) -> Self {
+        // Test attributes by their full path and by their name, in case they were imported
+        let mut needles = BTreeSet::new();
+        for attribute in KNOWN_TEST_ATTRIBUTES
+            .iter()
+            .copied()
+            .chain(options.test_attributes.iter().map(String::as_str))
+        {
+            needles.insert(format!("#[{attribute}"));
+            if let Some((_, name)) = attribute.rsplit_once("::") {
+                needles.insert(format!("#[{name}"));
             }
         }
+        if doc_tests {
+            // Doc tests need a code block, possibly in an included file
+            needles.extend(["```", "~~~", "include_str"].map(ToOwned::to_owned));
+        }
+        Self {
+            needles: needles
+                .into_iter()
+                .map(|n| Finder::new(n.as_bytes()).into_owned())
+                .collect(),
+            module: Finder::new(b"mod").into_owned(),
+        }
+    }
+
+    fn may_contain_tests(&self, source: &[u8]) -> bool {
+        self.needles.iter().any(|n| n.find(source).is_some())
+    }
+
+    fn may_declare_modules(&self, source: &[u8]) -> bool {
+        self.module.find(source).is_some()
+    }
+}
+
+/// Find tests in the source file at `path`, along with a problem that prevented finding
+/// (some of) them.
+fn parse_file(
+    path: &Path,
+    options: &DiscoveryOptions,
+    pre_filter: &PreFilter,
+) -> (ParsedSource, Option<Diagnostic>) {
+    let source = match std::fs::read(path) {
+        Ok(source) => source,
+        Err(e) => {
+            let diagnostic = Diagnostic::new(path, format!("couldn't read file: {e}"));
+            return (ParsedSource::default(), Some(diagnostic));
+        }
+    };
+    if !pre_filter.may_contain_tests(&source) {
+        if !pre_filter.may_declare_modules(&source) {
+            log::trace!("{path:?} contains no tests, skipping.");
+            return (ParsedSource::default(), None);
+        }
+        // Modules declared in the file are still followed to find the crates of other files
+        log::trace!("{path:?} contains no tests, only scanning it for modules.");
+        let found = ParsedSource {
+            modules: scan_modules(&String::from_utf8_lossy(&source)),
+            ..Default::default()
+        };
+        return (found, None);
+    }
+    let source = match String::from_utf8(source) {
+        Ok(source) => source,
+        Err(e) => {
+            let diagnostic = Diagnostic::new(path, format!("file isn't valid UTF-8: {e}"));
+            return (ParsedSource::default(), Some(diagnostic));
+        }
+    };
+    let dir = path.parent().unwrap_or(path);
+    match parse_source(&source, dir, &options.test_attributes) {
+        Ok(found) => (found, None),
+        Err(e) => {
+            let found = scan_source(&source, &options.test_attributes);
+            let diagnostic = Diagnostic::new(
+                path,
+                format!("couldn't parse file ({e}), fell back to scanning lines"),
+            );
+            (found, Some(diagnostic))
+        }
+    }
+}
+
+/// Get what's in the file at `path`, along with its stamp if it can be cached.
+///
+/// The file is only parsed if `cache` doesn't have it already, and only cached if there were
+/// no problems with it (so they are reported again next time).
+fn load_file(
+    path: &Path,
+    options: &DiscoveryOptions,
+    pre_filter: &PreFilter,
+    cache: Option<&TestCache>,
+    diagnostics: &Diagnostics,
+) -> (ParsedSource, Option<SourceStamp>) {
+    log::debug!("Getting tests from file: {path:?}");
+    let stamp = cache.and_then(|_| FileStamp::of(path));
+    if let Some(cached) = cache.zip(stamp.as_ref()).and_then(|(c, s)| c.get(path, s)) {
+        log::trace!("Using cached tests for {path:?}.");
+        return (cached.found.clone(), Some(cached.stamp.clone()));
+    }
+    let (found, diagnostic) = parse_file(path, options, pre_filter);
+    if let Some(diagnostic) = diagnostic {
+        diagnostics.push(diagnostic);
+        return (found, None);
+    }
+    let stamp = stamp.map(|stamp| SourceStamp::new(stamp, &found));
+    (found, stamp)
+}
+
+/// Crate a source file is compiled into, as one of its modules.
+#[derive(Debug, Clone)]
+struct ModuleOwner {
+    package: String,
+    scope: TestScope,
+    /// Path of the file's module within the crate.
+    module: Vec<String>,
+}
+
+/// Get the crates each source file is compiled into, following `mod` declarations (and their
+/// `#[path]` attributes) from the crate roots of `workspace`, like rustc does.
+///
+/// Files declared as modules that weren't `walked` are only loaded (with `load`) if walked
+/// files may be declared in them.
+fn module_owners(
+    workspace: &Workspace,
+    walked: &[PathBuf],
+    sources: &mut HashMap<PathBuf, (ParsedSource, Option<SourceStamp>)>,
+    mut load: impl FnMut(&Path) -> (ParsedSource, Option<SourceStamp>),
+) -> HashMap<PathBuf, Vec<ModuleOwner>> {
+    let mut owners = HashMap::<PathBuf, Vec<ModuleOwner>>::new();
+    for package in &workspace.packages {
+        for (scope, root) in &package.roots {
+            // Files to visit, with their module path and whether they own their directory
+            // (i.e. are crate roots, `mod.rs` files or loaded through `#[path]`)
+            let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+            let mut stack = vec![(root, vec![], true)];
+            let mut visited = HashSet::new();
+            while let Some((file, module, owns_dir)) = stack.pop() {
+                if !visited.insert(file.clone()) {
+                    continue;
+                }
+                let dir = file.parent().unwrap_or(&file).to_path_buf();
+                // Modules declared in `a.rs` are in `a/`
+                let module_dir = match file.file_stem() {
+                    Some(stem) if !owns_dir => dir.join(stem),
+                    _ => dir.clone(),
+                };
+                if !sources.contains_key(&file) {
+                    if !file.is_file() || !walked.iter().any(|w| w.starts_with(&module_dir)) {
+                        continue;
+                    }
+                    sources.insert(file.clone(), load(&file));
+                }
+                for declared in &sources[&file].0.modules {
+                    let Some(name) = declared.path.last() else {
+                        continue;
+                    };
+                    let declared_dir = declared
+                        .dirs
+                        .iter()
+                        .fold(module_dir.clone(), |dir, d| dir.join(d));
+                    let (child, owns_dir) = match &declared.file {
+                        // `#[path]`s outside of inline modules are relative to the file's directory
+                        Some(path) if declared.dirs.is_empty() => (dir.join(path), true),
+                        Some(path) => (declared_dir.join(path), true),
+                        None => {
+                            let file = declared_dir.join(format!("{name}.rs"));
+                            let mod_rs = declared_dir.join(name).join("mod.rs");
+                            if !file.is_file() && mod_rs.is_file() {
+                                (mod_rs, true)
+                            } else {
+                                (file, false)
+                            }
+                        }
+                    };
+                    let child_module = module.iter().chain(&declared.path).cloned().collect();
+                    let child = child.canonicalize().unwrap_or(child);
+                    stack.push((child, child_module, owns_dir));
+                }
+                owners.entry(file).or_default().push(ModuleOwner {
+                    package: package.name.clone(),
+                    scope: scope.clone(),
+                    module,
+                });
+            }
+        }
+    }
+    owners
+}
+
+/// Get tests found in the file at `path`, as compiled into the crate of `owner`.
+fn file_targets(path: &Path, found: &ParsedSource, owner: &ModuleOwner) -> Vec<Target> {
+    let mut tests = vec![];
+    for test in &found.tests {
+        let name = owner
+            .module
+            .iter()
+            .chain(&test.path)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("::");
+        let new_test = |name: String, parametrised: bool| {
+            Target::Test(TestTarget {
+                name,
+                path: path.to_path_buf(),
+                line: test.line,
+                column: test.column,
+                attributes: test.attributes.clone(),
+                parametrised,
+                ignored: test.ignored,
+                ignore_reason: test.ignore_reason.clone(),
+                should_panic: test.should_panic,
+                package: owner.package.clone(),
+                scope: owner.scope.clone(),
+            })
+        };
+        for case in &test.cases {
+            tests.push(new_test(format!("{name}::{case}"), false));
+        }
+        tests.push(new_test(name, test.parametrised));
+    }
+    // Rustdoc only runs doc tests of libraries
+    if owner.scope == TestScope::Lib {
+        for test in &found.doc_tests {
+            tests.push(Target::DocTest(DocTestTarget {
+                item: owner
+                    .module
+                    .iter()
+                    .chain(&test.item)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join("::"),
+                path: test.file.clone().unwrap_or_else(|| path.to_path_buf()),
+                line: test.line,
+                ignore: test.ignore,
+                no_run: test.no_run,
+                compile_fail: test.compile_fail,
+                package: owner.package.clone(),
+            }));
+        }
     }
     tests
 }
 
-pub fn get_tests_from_path(path: &Path) -> Vec<Target> {
+/// Get tests from source files under `path`, parsing them on multiple threads.
+///
+/// Files ignored by `.gitignore`/`.ignore`, outside of workspace members or excluded
+/// by `options` aren't searched. Only files with test attributes (or code blocks, if
+/// `doc_tests` are looked for) are parsed. Problems with files are added to `diagnostics`.
+pub(crate) fn get_tests_from_path(
+    path: &Path,
+    workspace: &Workspace,
+    options: &DiscoveryOptions,
+    doc_tests: bool,
+    diagnostics: &Diagnostics,
+) -> Result<Vec<Target>, ignore::Error> {
     log::debug!("Getting tests recursively from path: {path:?}");
-    let mut tests = vec![];
+    // Canonicalize to make paths comparable with the ones of workspace packages
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
 
-    let walker = WalkDir::new(path).into_iter();
-    //TODO: multithreading?
-    for entry in walker.filter_entry(is_rust_source_or_dir) {
-        if entry.as_ref().unwrap().file_type().is_file() {
-            tests.append(&mut get_tests_from_file(entry.as_ref().unwrap()));
+    let start = Instant::now();
+    let mut overrides = OverrideBuilder::new(&path);
+    for glob in &options.include {
+        overrides.add(glob)?;
+    }
+    for glob in &options.exclude {
+        overrides.add(&format!("!{glob}"))?;
+    }
+    let package_dirs = workspace
+        .packages
+        .iter()
+        .map(|p| p.path.clone())
+        .collect::<Vec<_>>();
+    let files = WalkBuilder::new(&path)
+        // Honour `.gitignore` outside of git repositories too
+        .require_git(false)
+        .overrides(overrides.build()?)
+        .filter_entry(move |entry| {
+            !entry.file_type().is_some_and(|t| t.is_dir())
+                || is_walkable_dir(entry.path(), &package_dirs)
+        })
+        .build()
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(&path, format!("couldn't walk: {e}")));
+                None
+            }
+        })
+        .filter(|entry| {
+            entry.file_type().is_some_and(|t| t.is_file())
+                && entry.path().extension().is_some_and(|e| e == "rs")
+        })
+        .map(DirEntry::into_path)
+        .collect::<Vec<PathBuf>>();
+    log::info!(
+        "Found {} source files in {:?}.",
+        files.len(),
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let target_directory = workspace
+        .target_directory
+        .as_deref()
+        .filter(|_| options.cache);
+    let cache =
+        target_directory.map(|dir| TestCache::load(dir, &options.test_attributes, doc_tests));
+    let pre_filter = PreFilter::new(options, doc_tests);
+    let next = AtomicUsize::new(0);
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(files.len().max(1));
+    log::debug!("Parsing on {threads} threads.");
+    let results = std::thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(file) = files.get(i) else {
+                            break results;
+                        };
+                        results.push((
+                            i,
+                            load_file(file, options, &pre_filter, cache.as_ref(), diagnostics),
+                        ));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("Test discovery thread panicked."))
+            .collect::<Vec<_>>()
+    });
+    let mut sources = results
+        .into_iter()
+        .map(|(i, loaded)| (files[i].clone(), loaded))
+        .collect::<HashMap<_, _>>();
+    let owners = module_owners(workspace, &files, &mut sources, |file| {
+        load_file(file, options, &pre_filter, cache.as_ref(), diagnostics)
+    });
+    // Keep the order of the walk, regardless of which thread parsed which file
+    let mut tests = vec![];
+    for file in &files {
+        let Some(file_owners) = owners.get(file) else {
+            log::debug!("{file:?} isn't a module of any crate, skipping.");
+            continue;
+        };
+        for owner in file_owners {
+            tests.append(&mut file_targets(file, &sources[file].0, owner));
         }
     }
-    tests
+    let mut new_cache = TestCache::new(&options.test_attributes, doc_tests);
+    for (file, (found, stamp)) in sources {
+        if let Some(stamp) = stamp {
+            new_cache.files.insert(file, CachedFile { stamp, found });
+        }
+    }
+    log::info!("Found {} tests in {:?}.", tests.len(), start.elapsed());
+    if let (Some(dir), Some(mut cache)) = (target_directory, cache) {
+        // Keep entries of files outside of `path`, drop the ones that don't exist anymore
+        cache
+            .files
+            .retain(|file, _| !file.starts_with(&path) && file.exists());
+        new_cache.files.extend(cache.files);
+        if let Err(e) = new_cache.save(dir) {
+            log::warn!("Couldn't save test cache: {e}");
+        }
+    }
+    Ok(tests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+    use crate::select::PackageLayout;
+
+    /// Owners of the `walked` files of a package with `roots` in `dir`, by relative path, as
+    /// their scope and module path joined with `::`.
+    fn owners(
+        dir: &Path,
+        roots: &[(TestScope, &str)],
+        walked: &[&str],
+    ) -> (Vec<(String, TestScope, String)>, Vec<String>) {
+        let workspace = Workspace {
+            packages: vec![PackageLayout {
+                name: "pkg".to_owned(),
+                path: dir.to_path_buf(),
+                roots: roots
+                    .iter()
+                    .map(|(scope, root)| (scope.clone(), dir.join(root)))
+                    .collect(),
+                default_member: true,
+            }],
+            ..Workspace::default()
+        };
+        let options = DiscoveryOptions::default();
+        let pre_filter = PreFilter::new(&options, true);
+        let diagnostics = Diagnostics::default();
+        let load = |file: &Path| load_file(file, &options, &pre_filter, None, &diagnostics);
+        let walked = walked.iter().map(|w| dir.join(w)).collect::<Vec<_>>();
+        let mut sources = walked
+            .iter()
+            .map(|file| (file.clone(), load(file)))
+            .collect::<HashMap<_, _>>();
+        let owners = module_owners(&workspace, &walked, &mut sources, load);
+        assert!(diagnostics.into_inner().is_empty());
+        let relative = |file: &Path| {
+            file.strip_prefix(dir)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        };
+        let mut owners = owners
+            .iter()
+            .flat_map(|(file, owners)| {
+                owners.iter().map(move |owner| {
+                    (relative(file), owner.scope.clone(), owner.module.join("::"))
+                })
+            })
+            .collect::<Vec<_>>();
+        owners.sort_by(|a, b| (&a.0, &a.2).cmp(&(&b.0, &b.2)));
+        let mut loaded = sources.keys().map(|f| relative(f)).collect::<Vec<_>>();
+        loaded.sort();
+        (owners, loaded)
+    }
+
+    #[test]
+    fn pre_filter_needs_test_attributes() {
+        let options = DiscoveryOptions::default();
+        let pre_filter = PreFilter::new(&options, false);
+        assert!(!pre_filter.may_contain_tests(b"fn test_helper() {}\n#[cfg(test)]\nmod tests;"));
+        assert!(pre_filter.may_contain_tests(b"#[tokio::test]\nasync fn works() {}"));
+        assert!(pre_filter.may_contain_tests(b"use tokio::test;\n#[test]\nasync fn works() {}"));
+        let documented = b"/// ```\n/// foo();\n/// ```\nfn foo() {}";
+        assert!(!pre_filter.may_contain_tests(documented));
+        assert!(PreFilter::new(&options, true).may_contain_tests(documented));
+    }
+
+    const LAYOUT: &[(&str, &str)] = &[
+        (
+            "src/lib.rs",
+            "mod a;\nmod b;\nmod inline {\n    #[path = \"c_file.rs\"]\n    mod c;\n}\n#[path = \"other/d.rs\"]\nmod d;\n",
+        ),
+        ("src/a.rs", "mod nested;\n"),
+        ("src/a/nested.rs", "#[test]\nfn works() {}\n"),
+        ("src/b/mod.rs", "pub mod inner;\n"),
+        ("src/b/inner.rs", ""),
+        ("src/inline/c_file.rs", ""),
+        ("src/other/d.rs", "mod e;\n"),
+        ("src/other/e.rs", ""),
+        ("src/orphan.rs", "#[test]\nfn orphaned() {}\n"),
+        ("src/main.rs", "mod a;\nfn main() {}\n"),
+    ];
+
+    #[test]
+    fn follows_module_declarations() {
+        let dir = temp_dir("modules", LAYOUT);
+        let walked = LAYOUT.iter().map(|(path, _)| *path).collect::<Vec<_>>();
+        let (owners, _) = owners(&dir, &[(TestScope::Lib, "src/lib.rs")], &walked);
+        let expected = [
+            ("src/a.rs", "a"),
+            ("src/a/nested.rs", "a::nested"),
+            ("src/b/inner.rs", "b::inner"),
+            ("src/b/mod.rs", "b"),
+            ("src/inline/c_file.rs", "inline::c"),
+            ("src/lib.rs", ""),
+            ("src/other/d.rs", "d"),
+            ("src/other/e.rs", "d::e"),
+        ]
+        .map(|(file, module)| (file.to_owned(), TestScope::Lib, module.to_owned()));
+        assert_eq!(owners, expected);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn files_of_several_crates() {
+        let dir = temp_dir("several", LAYOUT);
+        let walked = LAYOUT.iter().map(|(path, _)| *path).collect::<Vec<_>>();
+        let roots = [
+            (TestScope::Lib, "src/lib.rs"),
+            (TestScope::Bin("pkg".to_owned()), "src/main.rs"),
+        ];
+        let (owners, _) = owners(&dir, &roots, &walked);
+        let nested = owners
+            .iter()
+            .filter(|(file, ..)| file == "src/a/nested.rs")
+            .map(|(_, scope, module)| (scope.clone(), module.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nested,
+            [
+                (TestScope::Lib, "a::nested"),
+                (TestScope::Bin("pkg".to_owned()), "a::nested")
+            ]
+        );
+        assert!(owners.iter().all(|(file, ..)| file != "src/orphan.rs"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn loads_only_parents_of_walked_files() {
+        let dir = temp_dir("walked", LAYOUT);
+        let (owners, loaded) = owners(
+            &dir,
+            &[(TestScope::Lib, "src/lib.rs")],
+            &["src/a/nested.rs"],
+        );
+        assert!(owners.contains(&(
+            "src/a/nested.rs".to_owned(),
+            TestScope::Lib,
+            "a::nested".to_owned()
+        )));
+        assert_eq!(loaded, ["src/a.rs", "src/a/nested.rs", "src/lib.rs"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,148 @@
+//! Discovery and fuzzy matching of the targets of a Cargo workspace (binaries, examples, tests,
+//! doc tests, ...), as done by `cargo select`.
+//!
+//! ```no_run
+//! use cargo_select::{discover, find_manifest_dir, match_targets, DiscoveryOptions, TargetKind};
+//!
+//! let dir = find_manifest_dir(None)?;
+//! let kinds = [TargetKind::Bin, TargetKind::Test];
+//! let workspace = discover(&dir, &kinds, &DiscoveryOptions::default())?;
+//! for m in match_targets(&workspace.targets, "parse", None) {
+//!     println!("{} {}", m.score, m.target);
+//! }
+//! # Ok::<(), cargo_select::SelectError>(())
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+mod cache;
+mod diagnostics;
+mod doc_tests;
+mod error;
+#[cfg(test)]
+mod fixtures;
+mod get_benches;
+mod get_tests;
+mod metadata;
+mod select;
+mod test_parser;
+
+pub use diagnostics::{Diagnostic, DiagnosticKind};
+pub use error::SelectError;
+pub use get_tests::DiscoveryOptions;
+pub use select::{
+    match_targets, near_misses, BenchFunctionTarget, DocTestTarget, Match, PackageLayout,
+    RunTarget, Target, TargetKind, TestScope, TestTarget, Workspace,
+};
+
+use cache::TestCache;
+use diagnostics::Diagnostics;
+use get_benches::get_bench_functions;
+use get_tests::get_tests_from_path;
+use metadata::workspace_from_metadata;
+use select::{find_workspace_root, new_complete_manifest_from_path, workspace_from_manifest};
+
+/// Get the directory of the manifest to start from: the one at `manifest_path`, or the
+/// nearest one in the current directory or its parents.
+pub fn find_manifest_dir(manifest_path: Option<&Path>) -> Result<PathBuf, SelectError> {
+    if let Some(manifest_path) = manifest_path {
+        if !manifest_path.is_file() {
+            return Err(SelectError::ManifestNotFound {
+                path: manifest_path.to_path_buf(),
+            });
+        }
+        return Ok(manifest_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf));
+    }
+    let current_dir = std::env::current_dir().map_err(|e| SelectError::io(Path::new("."), e))?;
+    current_dir
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+        .ok_or(SelectError::ManifestNotFound { path: current_dir })
+}
+
+/// Discover the workspace of the package at `path`, preferring `cargo metadata` over reading
+/// manifests.
+fn discover_workspace(path: &Path, default_members: bool) -> Result<Workspace, SelectError> {
+    let start = Instant::now();
+    let mut workspace = match workspace_from_metadata(path) {
+        Ok(workspace) => workspace,
+        Err(e) => {
+            log::warn!("{e}");
+            log::warn!("Falling back to reading manifests directly.");
+            let root = find_workspace_root(path)?;
+            let manifest = new_complete_manifest_from_path(&root)?;
+            workspace_from_manifest(&manifest, &root)
+        }
+    };
+    log::info!(
+        "Found {} packages and {} targets in {:?}.",
+        workspace.packages.len(),
+        workspace.targets.len(),
+        start.elapsed()
+    );
+    if default_members {
+        workspace.retain_default_members();
+    }
+    Ok(workspace)
+}
+
+/// Discover the workspace of the package at `path`, with its targets of `kinds`.
+///
+/// Test and bench functions are found in source files if their kinds are asked for. Problems
+/// that didn't stop discovery (e.g. unparseable files) are in the workspace's `diagnostics`.
+pub fn discover(
+    path: &Path,
+    kinds: &[TargetKind],
+    options: &DiscoveryOptions,
+) -> Result<Workspace, SelectError> {
+    let mut workspace = discover_workspace(path, options.default_members)?;
+    let diagnostics = Diagnostics::default();
+    diagnostics.extend(std::mem::take(&mut workspace.diagnostics));
+    let found = std::mem::take(&mut workspace.targets);
+    let mut functions = if kinds.contains(&TargetKind::BenchFunction) {
+        get_bench_functions(&found, &diagnostics)
+    } else {
+        vec![]
+    };
+    let mut targets = found
+        .into_iter()
+        .filter(|t| kinds.contains(&t.kind()))
+        .collect::<Vec<_>>();
+    targets.append(&mut functions);
+    if kinds.contains(&TargetKind::Test) || kinds.contains(&TargetKind::DocTest) {
+        let doc_tests = kinds.contains(&TargetKind::DocTest);
+        let tests = get_tests_from_path(
+            &workspace.root,
+            &workspace,
+            options,
+            doc_tests,
+            &diagnostics,
+        )
+        .map_err(|e| SelectError::Discovery {
+            message: e.to_string(),
+        })?;
+        targets.extend(tests.into_iter().filter(|t| kinds.contains(&t.kind())));
+    }
+    workspace.targets = targets;
+    workspace.diagnostics = diagnostics.into_inner();
+    Ok(workspace)
+}
+
+/// Remove tests cached by discovery from the target directory of `workspace`, returning
+/// whether there were any.
+pub fn clear_cache(workspace: &Workspace) -> Result<bool, SelectError> {
+    let Some(target_directory) = workspace.target_directory.as_deref() else {
+        return Err(SelectError::Discovery {
+            message: "couldn't find the target directory".to_owned(),
+        });
+    };
+    TestCache::clear(target_directory)
+        .map_err(|e| SelectError::io(&TestCache::path(target_directory), e))
+}
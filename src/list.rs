@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use cargo_select::Target;
+
+/// Fields of a target printed by `cargo select list`.
+#[derive(Debug, Serialize)]
+pub struct TargetRecord {
+    pub kind: &'static str,
+    pub package: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// How well the target matched the pattern, if there was one.
+    pub score: Option<i64>,
+}
+
+impl TargetRecord {
+    pub fn new(target: &Target, score: Option<i64>) -> Self {
+        let (path, line, column) = match target {
+            Target::Test(t) => (t.path.clone(), Some(t.line), Some(t.column)),
+            Target::DocTest(t) => (t.path.clone(), Some(t.line), None),
+            _ => {
+                let t = target
+                    .run_target()
+                    .expect("Every other target is run through a cargo target.");
+                (t.path.clone(), None, None)
+            }
+        };
+        Self {
+            kind: target.kind().id(),
+            package: target.package().to_owned(),
+            name: target.name().to_owned(),
+            path,
+            line,
+            column,
+            score,
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "kind" => self.kind.to_owned(),
+            "package" => self.package.clone(),
+            "name" => self.name.clone(),
+            "path" => self.path.to_string_lossy().to_string(),
+            "line" => self.line.map(|l| l.to_string()).unwrap_or_default(),
+            "column" => self.column.map(|c| c.to_string()).unwrap_or_default(),
+            "score" => self.score.map(|s| s.to_string()).unwrap_or_default(),
+            _ => return None,
+        })
+    }
+
+    /// Fill in `{field}` placeholders of `template`, leaving missing fields empty and unknown
+    /// placeholders as they are.
+    ///
+    /// `\t`, `\n` and `\\` are unescaped, so templates can be passed without shell quoting tricks.
+    pub fn format(&self, template: &str) -> String {
+        let mut ret = String::new();
+        let mut rest = template;
+        while let Some(c) = rest.chars().next() {
+            let placeholder = rest
+                .strip_prefix('{')
+                .and_then(|r| r.split_once('}'))
+                .and_then(|(name, after)| Some((self.field(name)?, after)));
+            if let Some((value, after)) = placeholder {
+                ret.push_str(&value);
+                rest = after;
+                continue;
+            }
+            let escaped = match rest.get(..2) {
+                Some(r"\t") => Some('\t'),
+                Some(r"\n") => Some('\n'),
+                Some(r"\\") => Some('\\'),
+                _ => None,
+            };
+            if let Some(escaped) = escaped {
+                ret.push(escaped);
+                rest = &rest[2..];
+                continue;
+            }
+            ret.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        ret
+    }
+}
@@ -1,9 +1,4 @@
-use std::{
-    error::Error,
-    sync::{Arc, Mutex},
-};
-
-use clap::Parser;
+use std::sync::{Arc, Mutex};
 
 use flexi_logger::Logger;
 
@@ -11,9 +6,12 @@ mod cli;
 use cli::Cli;
 use logging::LogVec;
 
-mod get_tests;
+mod aliases;
+mod commands;
+mod exit;
+mod list;
 mod logging;
-mod select;
+mod shell;
 mod tui;
 
 fn init_logger(logger: LogVec) {
@@ -24,13 +22,19 @@ fn init_logger(logger: LogVec) {
         .unwrap();
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let logs = Arc::new(Mutex::new(Vec::new()));
     let logger = LogVec::new(Arc::clone(&logs));
     init_logger(logger);
-    let ret = Cli::parse().exec();
+    let cli = Cli::parse_args(std::env::args_os());
+    let error_format = cli.error_format();
+    let ret = cli.exec();
     for log in logs.lock().unwrap().iter() {
-        println!("{log}");
+        eprintln!("{log}");
     }
-    ret
+    let code = ret.unwrap_or_else(|error| {
+        error_format.print(&error);
+        error.exit_code()
+    });
+    std::process::exit(code);
 }
@@ -0,0 +1,166 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+
+use crate::select::{PackageLayout, RunTarget, Target, TestScope, Workspace};
+
+/// Subset of the `cargo metadata --format-version 1` output we care about.
+#[derive(Deserialize, Debug)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    /// Missing with Cargo versions older than 1.71.
+    #[serde(default)]
+    workspace_default_members: Option<Vec<String>>,
+    target_directory: PathBuf,
+    workspace_root: PathBuf,
+}
+
+#[derive(Deserialize, Debug)]
+struct Package {
+    id: String,
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<MetadataTarget>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MetadataTarget {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+    #[serde(default, rename = "required-features")]
+    required_features: Vec<String>,
+}
+
+impl MetadataTarget {
+    fn is_kind(&self, kind: &str) -> bool {
+        self.kind.iter().any(|k| k == kind)
+    }
+
+    fn to_scope(&self) -> Option<TestScope> {
+        const LIB_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+        if self.kind.iter().any(|k| LIB_KINDS.contains(&k.as_str())) {
+            return Some(TestScope::Lib);
+        }
+        let name = self.name.clone();
+        Some(match self.kind.first()?.as_str() {
+            "bin" => TestScope::Bin(name),
+            "example" => TestScope::Example(name),
+            "test" => TestScope::Test(name),
+            "bench" => TestScope::Bench(name),
+            _ => return None,
+        })
+    }
+
+    fn to_run_target(&self, package: &Package) -> RunTarget {
+        RunTarget {
+            name: self.name.clone(),
+            package: package.name.clone(),
+            path: self.src_path.clone(),
+            workspace_path: package
+                .manifest_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            package_id: Some(package.id.clone()),
+            manifest_path: package.manifest_path.clone(),
+            required_features: self.required_features.clone(),
+        }
+    }
+}
+
+fn cargo_metadata(path: &Path) -> Result<Metadata, Box<dyn Error>> {
+    let manifest_path = path.join("Cargo.toml");
+    log::info!("Running `cargo metadata` for {manifest_path:?}");
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .arg("--manifest-path")
+        .arg(&manifest_path)
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Get targets and layouts of every workspace member, as reported by `cargo metadata`.
+pub fn workspace_from_metadata(path: &Path) -> Result<Workspace, Box<dyn Error>> {
+    log::debug!("Getting targets from `cargo metadata`.");
+    let metadata = cargo_metadata(path)?;
+    let mut ret = Workspace {
+        root: metadata.workspace_root.clone(),
+        target_directory: Some(metadata.target_directory.clone()),
+        ..Default::default()
+    };
+    for package in metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+    {
+        log::debug!("Handling package: {} ({}).", package.name, package.id);
+        ret.packages.push(PackageLayout {
+            name: package.name.clone(),
+            path: package
+                .manifest_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            roots: package
+                .targets
+                .iter()
+                .filter_map(|t| Some((t.to_scope()?, t.src_path.clone())))
+                .collect(),
+            default_member: metadata
+                .workspace_default_members
+                .as_ref()
+                .map_or(true, |members| members.contains(&package.id)),
+        });
+        let dir = package.manifest_path.parent().unwrap_or(Path::new("."));
+        let target = Target::Package(RunTarget {
+            package_id: Some(package.id.clone()),
+            manifest_path: package.manifest_path.clone(),
+            ..RunTarget::package(&package.name, dir)
+        });
+        log::debug!("Adding target: {}", target);
+        ret.targets.push(target);
+        for lib in package
+            .targets
+            .iter()
+            .filter(|t| t.to_scope() == Some(TestScope::Lib))
+        {
+            let target = Target::Lib(lib.to_run_target(package));
+            log::debug!("Adding target: {}", target);
+            ret.targets.push(target);
+        }
+        for bin in package.targets.iter().filter(|t| t.is_kind("bin")) {
+            let target = Target::Bin(bin.to_run_target(package));
+            log::debug!("Adding target: {}", target);
+            ret.targets.push(target);
+        }
+        for example in package.targets.iter().filter(|t| t.is_kind("example")) {
+            let target = Target::Example(example.to_run_target(package));
+            log::debug!("Adding target: {}", target);
+            ret.targets.push(target);
+        }
+        for test in package.targets.iter().filter(|t| t.is_kind("test")) {
+            let target = Target::IntegrationTest(test.to_run_target(package));
+            log::debug!("Adding target: {}", target);
+            ret.targets.push(target);
+        }
+        for bench in package.targets.iter().filter(|t| t.is_kind("bench")) {
+            let target = Target::Bench(bench.to_run_target(package));
+            log::debug!("Adding target: {}", target);
+            ret.targets.push(target);
+        }
+    }
+    Ok(ret)
+}
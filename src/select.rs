@@ -2,27 +2,159 @@ use cargo_toml::{Manifest, Product};
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use std::{
     cmp::Reverse,
-    error::Error,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Diagnostic;
+use crate::error::SelectError;
+
+/// Something that can be selected and run, e.g. a binary or a test function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum Target {
+    /// Whole package, for commands acting on packages (e.g. `cargo doc`).
+    Package(RunTarget),
+    Lib(RunTarget),
     Bin(RunTarget),
     Example(RunTarget),
+    Bench(RunTarget),
+    /// Integration test crate, e.g. `tests/foo.rs`.
+    IntegrationTest(RunTarget),
+    /// Benchmark function registered with criterion, used as a filter for its bench target.
+    BenchFunction(BenchFunctionTarget),
     Test(TestTarget),
+    /// Code block in documentation, run by rustdoc.
+    DocTest(DocTestTarget),
+}
+
+/// Kind of a [`Target`], without its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TargetKind {
+    Package,
+    Lib,
+    Bin,
+    Example,
+    Bench,
+    IntegrationTest,
+    BenchFunction,
+    Test,
+    DocTest,
+}
+
+impl TargetKind {
+    /// Identifier of the kind in machine-readable output, e.g. `integration_test`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            TargetKind::Package => "package",
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Bench => "bench",
+            TargetKind::IntegrationTest => "integration_test",
+            TargetKind::BenchFunction => "bench_function",
+            TargetKind::Test => "test",
+            TargetKind::DocTest => "doc_test",
+        }
+    }
+}
+
+impl std::fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TargetKind::Package => "package",
+                TargetKind::Lib => "library",
+                TargetKind::Bin => "binary",
+                TargetKind::Example => "example",
+                TargetKind::Bench => "bench",
+                TargetKind::IntegrationTest => "integration test",
+                TargetKind::BenchFunction => "bench function",
+                TargetKind::Test => "test",
+                TargetKind::DocTest => "doc test",
+            }
+        )
+    }
 }
 
 impl Target {
-    pub fn to_cargo_flag(&self) -> &'static str {
+    pub fn kind(&self) -> TargetKind {
+        match self {
+            Target::Package(_) => TargetKind::Package,
+            Target::Lib(_) => TargetKind::Lib,
+            Target::Bin(_) => TargetKind::Bin,
+            Target::Example(_) => TargetKind::Example,
+            Target::Bench(_) => TargetKind::Bench,
+            Target::IntegrationTest(_) => TargetKind::IntegrationTest,
+            Target::BenchFunction(_) => TargetKind::BenchFunction,
+            Target::Test(_) => TargetKind::Test,
+            Target::DocTest(_) => TargetKind::DocTest,
+        }
+    }
+
+    /// Flag selecting this kind of cargo target, e.g. `--bin`, or `None` for packages and tests.
+    pub fn to_cargo_flag(&self) -> Option<&'static str> {
+        match self {
+            Target::Lib(_) => Some("--lib"),
+            Target::Bin(_) => Some("--bin"),
+            Target::Example(_) => Some("--example"),
+            Target::Bench(_) | Target::BenchFunction(_) => Some("--bench"),
+            Target::IntegrationTest(_) => Some("--test"),
+            Target::Package(_) | Target::Test(_) | Target::DocTest(_) => None,
+        }
+    }
+
+    /// The cargo target (or package) this target is run through, if any.
+    pub fn run_target(&self) -> Option<&RunTarget> {
+        match self {
+            Target::Package(t)
+            | Target::Lib(t)
+            | Target::Bin(t)
+            | Target::Example(t)
+            | Target::Bench(t)
+            | Target::IntegrationTest(t) => Some(t),
+            Target::BenchFunction(t) => Some(&t.bench),
+            Target::Test(_) | Target::DocTest(_) => None,
+        }
+    }
+
+    /// Short name of the target, e.g. a binary's name or a test's path.
+    pub fn name(&self) -> &str {
         match self {
-            Target::Bin(_) => "--package",
-            Target::Example(_) => "--example",
-            Target::Test(_) => panic!("No cargo flag for test!"),
+            Target::BenchFunction(t) => &t.name,
+            Target::Test(t) => &t.name,
+            Target::DocTest(t) => &t.item,
+            _ => &self.run_target().expect("Target has no name!").name,
         }
     }
 
-    pub fn fuzzy_match(&self, pattern: &str, skim: &SkimMatcherV2) -> i64 {
+    /// Name of the package this target belongs to.
+    pub fn package(&self) -> &str {
+        match self {
+            Target::Test(t) => &t.package,
+            Target::DocTest(t) => &t.package,
+            _ => &self.run_target().expect("Target has no package!").package,
+        }
+    }
+
+    /// Arguments selecting the target within its package, e.g. `--bin NAME`, or `None` for tests,
+    /// which aren't run through a cargo target of their own.
+    pub fn to_target_args(&self) -> Option<Vec<String>> {
+        let t = self.run_target()?;
+        Some(match self {
+            Target::Package(_) => vec![],
+            Target::Lib(_) => vec!["--lib".to_owned()],
+            _ => vec![self.to_cargo_flag()?.to_owned(), t.name.clone()],
+        })
+    }
+
+    pub(crate) fn fuzzy_match(&self, pattern: &str, skim: &SkimMatcherV2) -> i64 {
         skim.fuzzy_match(&self.to_string(), pattern).unwrap_or(-1)
     }
 }
@@ -33,130 +165,697 @@ impl std::fmt::Display for Target {
             f,
             "{}",
             match self {
+                Target::Package(t) => format!("Package: {}", t),
+                Target::Lib(t) => format!("Library: {}", t),
                 Target::Bin(t) => format!("Binary: {}", t),
                 Target::Example(t) => format!("Example: {}", t),
+                Target::Bench(t) => format!("Bench: {}", t),
+                Target::BenchFunction(t) => format!("Bench function: {}", t),
+                Target::IntegrationTest(t) => format!("Integration test: {}", t),
                 Target::Test(t) => format!("Test: {}", t),
+                Target::DocTest(t) => format!("Doc test: {}", t),
             }
         )
     }
 }
 
-#[derive(Debug)]
+/// Cargo target (or package) as a whole, e.g. a binary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct RunTarget {
     pub name: String,
-    pub path: String,
+    /// Name of the package this target belongs to.
+    pub package: String,
+    pub path: PathBuf,
     pub workspace_path: PathBuf,
+    /// Package id as reported by `cargo metadata`, if that backend was used.
+    pub package_id: Option<String>,
+    pub manifest_path: PathBuf,
+    /// Features that have to be enabled to build the target.
+    pub required_features: Vec<String>,
 }
 
 impl RunTarget {
-    pub fn new(product: &Product, path: &Path) -> Self {
+    pub(crate) fn new(product: &Product, kind: TargetKind, path: &Path, package: &str) -> Self {
         log::debug!("{:?}", path);
         log::debug!("{:?}", product.path);
         Self {
             name: product.name.to_owned().unwrap_or_default(),
-            path: path
-                .join(product.path.to_owned().unwrap_or_default())
-                .to_string_lossy()
-                .to_string(),
+            package: package.to_owned(),
+            path: product_path(product, kind, path, package),
             workspace_path: PathBuf::from(path),
+            package_id: None,
+            manifest_path: path.join("Cargo.toml"),
+            required_features: product.required_features.clone(),
+        }
+    }
+
+    /// Target standing for the whole package `name` at `path`.
+    pub fn package(name: &str, path: &Path) -> Self {
+        Self {
+            name: name.to_owned(),
+            package: name.to_owned(),
+            path: path.to_path_buf(),
+            workspace_path: path.to_path_buf(),
+            package_id: None,
+            manifest_path: path.join("Cargo.toml"),
+            required_features: vec![],
         }
     }
 }
 
+/// Get the source file of `product` in the package `package` at `path`, inferring it the way
+/// Cargo does if the manifest doesn't set it, e.g. `examples/<name>.rs` for an `[[example]]`.
+fn product_path(product: &Product, kind: TargetKind, path: &Path, package: &str) -> PathBuf {
+    if let Some(product_path) = &product.path {
+        return path.join(product_path);
+    }
+    let name = product.name.as_deref().unwrap_or_default();
+    let dir = match kind {
+        TargetKind::Lib => return path.join("src").join("lib.rs"),
+        TargetKind::Bin if name == package && path.join("src").join("main.rs").is_file() => {
+            return path.join("src").join("main.rs")
+        }
+        TargetKind::Bin => path.join("src").join("bin"),
+        TargetKind::Example => path.join("examples"),
+        TargetKind::IntegrationTest => path.join("tests"),
+        TargetKind::Bench => path.join("benches"),
+        _ => return path.to_path_buf(),
+    };
+    let file = dir.join(format!("{name}.rs"));
+    let main = dir.join(name).join("main.rs");
+    if !file.is_file() && main.is_file() {
+        main
+    } else {
+        file
+    }
+}
+
 impl std::fmt::Display for RunTarget {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:30}\t({})", self.name, self.path)
+        write!(f, "{:30}\t({})", self.name, self.path.to_string_lossy())?;
+        if !self.required_features.is_empty() {
+            write!(f, " [requires: {}]", self.required_features.join(", "))?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BenchFunctionTarget {
+    pub name: String,
+    /// Bench target the function is registered in.
+    pub bench: RunTarget,
+}
+
+impl std::fmt::Display for BenchFunctionTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:30}\t({})",
+            self.name,
+            self.bench.path.to_string_lossy()
+        )
+    }
+}
+
+/// Cargo target a test function is compiled into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TestScope {
+    Lib,
+    Bin(String),
+    Example(String),
+    Test(String),
+    Bench(String),
+}
+
+impl TestScope {
+    /// Arguments narrowing a cargo command to this target, e.g. `--lib` or `--test NAME`.
+    pub fn to_cargo_args(&self) -> Vec<String> {
+        let (flag, name) = match self {
+            TestScope::Lib => return vec!["--lib".to_owned()],
+            TestScope::Bin(name) => ("--bin", name),
+            TestScope::Example(name) => ("--example", name),
+            TestScope::Test(name) => ("--test", name),
+            TestScope::Bench(name) => ("--bench", name),
+        };
+        vec![flag.to_owned(), name.clone()]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct TestTarget {
     pub name: String,
     pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    /// Paths of the test function's attributes, e.g. `test` or `should_panic`.
+    pub attributes: Vec<String>,
+    /// Whether this is a group of generated test cases rather than a single test.
+    pub parametrised: bool,
+    /// Whether the test is marked with `#[ignore]`.
+    pub ignored: bool,
+    /// Reason given with `#[ignore = "reason"]`.
+    pub ignore_reason: Option<String>,
+    pub should_panic: bool,
+    /// Name of the package the test belongs to.
+    pub package: String,
+    pub scope: TestScope,
 }
 
 impl std::fmt::Display for TestTarget {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:30}\t({})", self.name, self.path.to_str().unwrap())
+        write!(
+            f,
+            "{:30}\t({}:{}:{})",
+            self.name,
+            self.path.to_string_lossy(),
+            self.line,
+            self.column
+        )?;
+        match (&self.ignore_reason, self.ignored) {
+            (Some(reason), _) => write!(f, " [ignored: {reason}]")?,
+            (None, true) => write!(f, " [ignored]")?,
+            (None, false) => {}
+        }
+        if self.should_panic {
+            write!(f, " [should_panic]")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DocTestTarget {
+    /// Path of the documented item within its crate, empty for the crate root.
+    pub item: String,
+    /// File containing the code block.
+    pub path: PathBuf,
+    pub line: usize,
+    pub ignore: bool,
+    pub no_run: bool,
+    pub compile_fail: bool,
+    /// Name of the package the test belongs to.
+    pub package: String,
+}
+
+impl DocTestTarget {
+    /// Filter selecting this test among rustdoc's test names (`<file> - <item> (line <N>)`).
+    ///
+    /// Rustdoc splits test arguments on whitespace, so only the item can be matched on,
+    /// and other code blocks documenting it run too. Crate docs can't be narrowed down at all.
+    pub fn filter(&self) -> Option<&str> {
+        (!self.item.is_empty()).then_some(self.item.as_str())
     }
 }
 
-pub fn targets_from_manifest(manifest: &Manifest, path: &Path) -> Vec<Target> {
+impl std::fmt::Display for DocTestTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = if self.item.is_empty() {
+            &self.package
+        } else {
+            &self.item
+        };
+        write!(
+            f,
+            "{:30}\t({}:{})",
+            name,
+            self.path.to_string_lossy(),
+            self.line
+        )?;
+        for (marker, set) in [
+            ("ignore", self.ignore),
+            ("no_run", self.no_run),
+            ("compile_fail", self.compile_fail),
+        ] {
+            if set {
+                write!(f, " [{marker}]")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Crate roots of a package, used to find out which target a source file is compiled into.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PackageLayout {
+    pub name: String,
+    /// Directory containing the package manifest.
+    pub path: PathBuf,
+    pub roots: Vec<(TestScope, PathBuf)>,
+    /// Whether the package is one of the workspace's `default-members`.
+    pub default_member: bool,
+}
+
+impl PackageLayout {
+    pub(crate) fn from_manifest(manifest: &Manifest, path: &Path) -> Option<Self> {
+        let name = manifest.package.as_ref()?.name.clone();
+        // Canonicalize to make paths comparable with the ones `cargo metadata` reports
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut roots = vec![];
+        if let Some(lib) = &manifest.lib {
+            roots.push((
+                TestScope::Lib,
+                product_path(lib, TargetKind::Lib, &path, &name),
+            ));
+        }
+        let products = [
+            (
+                &manifest.bin,
+                TargetKind::Bin,
+                TestScope::Bin as fn(String) -> TestScope,
+            ),
+            (&manifest.test, TargetKind::IntegrationTest, TestScope::Test),
+            (&manifest.example, TargetKind::Example, TestScope::Example),
+            (&manifest.bench, TargetKind::Bench, TestScope::Bench),
+        ];
+        for (products, kind, scope) in products {
+            for product in products {
+                if let Some(product_name) = &product.name {
+                    let product_path = product_path(product, kind, &path, &name);
+                    roots.push((scope(product_name.clone()), product_path));
+                }
+            }
+        }
+        Some(Self {
+            name,
+            path,
+            roots,
+            default_member: true,
+        })
+    }
+}
+
+/// Targets and packages discovered in a workspace.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Workspace {
+    pub targets: Vec<Target>,
+    pub packages: Vec<PackageLayout>,
+    /// Directory build artifacts (and our cache) go in.
+    pub target_directory: Option<PathBuf>,
+    /// Root directory of the workspace.
+    pub root: PathBuf,
+    /// Problems with workspace members that were skipped.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Workspace {
+    pub(crate) fn append(&mut self, mut other: Workspace) {
+        self.targets.append(&mut other.targets);
+        self.packages.append(&mut other.packages);
+        self.diagnostics.append(&mut other.diagnostics);
+        if self.target_directory.is_none() {
+            self.target_directory = other.target_directory;
+        }
+    }
+
+    /// Leave out packages that aren't default members of the workspace, along with their targets.
+    pub fn retain_default_members(&mut self) {
+        self.packages.retain(|p| p.default_member);
+        let packages = &self.packages;
+        self.targets
+            .retain(|t| packages.iter().any(|p| p.name == t.package()));
+    }
+
+    /// Get the package `file` belongs to, i.e. the one with the closest directory.
+    pub fn package_of(&self, file: &Path) -> Option<&PackageLayout> {
+        self.packages
+            .iter()
+            .filter(|p| file.starts_with(&p.path))
+            .max_by_key(|p| p.path.components().count())
+    }
+}
+
+pub(crate) fn workspace_from_manifest(manifest: &Manifest, path: &Path) -> Workspace {
     log::debug!("Getting targets from manifest.");
-    let mut ret = vec![];
+    let mut ret = Workspace {
+        root: path.to_path_buf(),
+        target_directory: Some(path.join("target")),
+        ..Default::default()
+    };
+    if let Some(layout) = PackageLayout::from_manifest(manifest, path) {
+        ret.packages.push(layout);
+    }
+    let package = manifest
+        .package
+        .as_ref()
+        .map(|p| p.name.as_str())
+        .unwrap_or_default();
+    if manifest.package.is_some() {
+        let target = Target::Package(RunTarget::package(package, path));
+        log::debug!("Adding target: {}", target);
+        ret.targets.push(target);
+    }
+    if let Some(lib) = &manifest.lib {
+        let target = Target::Lib(RunTarget::new(lib, TargetKind::Lib, path, package));
+        log::debug!("Adding target: {}", target);
+        ret.targets.push(target);
+    }
     for bin in &manifest.bin {
-        let target = Target::Bin(RunTarget::new(bin, path));
+        let target = Target::Bin(RunTarget::new(bin, TargetKind::Bin, path, package));
         log::debug!("Adding target: {}", target);
-        ret.push(target);
+        ret.targets.push(target);
     }
     for example in manifest.example.iter() {
-        let target = Target::Example(RunTarget::new(example, path));
+        let target = Target::Example(RunTarget::new(example, TargetKind::Example, path, package));
+        log::debug!("Adding target: {}", target);
+        ret.targets.push(target);
+    }
+    for test in manifest.test.iter() {
+        let target = Target::IntegrationTest(RunTarget::new(
+            test,
+            TargetKind::IntegrationTest,
+            path,
+            package,
+        ));
+        log::debug!("Adding target: {}", target);
+        ret.targets.push(target);
+    }
+    for bench in manifest.bench.iter() {
+        let target = Target::Bench(RunTarget::new(bench, TargetKind::Bench, path, package));
         log::debug!("Adding target: {}", target);
-        ret.push(target);
+        ret.targets.push(target);
     }
     if let Some(workspace) = &manifest.workspace {
-        for member in &workspace.members {
+        let root = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let members = expand_members(path, &workspace.members, &workspace.exclude, &mut ret);
+        for member_path in members {
             // Prevent loops
-            if member == "." || member == "/." {
+            if member_path.canonicalize().ok().as_ref() == Some(&root) {
+                continue;
+            }
+            log::debug!("Handling workspace member: {member_path:?}.");
+            match new_complete_manifest_from_path(&member_path) {
+                Ok(manifest) => ret.append(workspace_from_manifest(&manifest, &member_path)),
+                Err(SelectError::ManifestNotFound { .. }) => ret
+                    .diagnostics
+                    .push(Diagnostic::missing_member(&member_path)),
+                Err(e) => ret.diagnostics.push(Diagnostic::new(
+                    &member_path,
+                    format!("couldn't read workspace member manifest: {e}"),
+                )),
+            }
+        }
+        // Like Cargo, default to the root package if there is one, otherwise to every member
+        let default_members = if !workspace.default_members.is_empty() {
+            Some(expand_members(
+                path,
+                &workspace.default_members,
+                &[],
+                &mut ret,
+            ))
+        } else if manifest.package.is_some() {
+            Some(vec![path.to_path_buf()])
+        } else {
+            None
+        };
+        if let Some(default_members) = default_members {
+            let default_members = default_members
+                .iter()
+                .map(|p| p.canonicalize().unwrap_or_else(|_| p.clone()))
+                .collect::<Vec<_>>();
+            for package in &mut ret.packages {
+                package.default_member = default_members.contains(&package.path);
+            }
+        }
+    }
+    ret
+}
+
+/// Find the root of the workspace the package at `path` belongs to, the same way Cargo does.
+pub(crate) fn find_workspace_root(path: &Path) -> Result<PathBuf, SelectError> {
+    let manifest_path = path.join("Cargo.toml");
+    let manifest =
+        Manifest::from_path(&manifest_path).map_err(|e| manifest_error(manifest_path, e))?;
+    if manifest.workspace.is_some() {
+        return Ok(path.to_path_buf());
+    }
+    if let Some(root) = manifest.package.as_ref().and_then(|p| p.workspace.as_ref()) {
+        return Ok(path.join(root));
+    }
+    let canonical = path.canonicalize().map_err(|e| SelectError::Discovery {
+        message: format!("couldn't resolve {}: {e}", path.display()),
+    })?;
+    for dir in canonical.ancestors().skip(1) {
+        let Ok(candidate) = Manifest::from_path(dir.join("Cargo.toml")) else {
+            continue;
+        };
+        let Some(workspace) = &candidate.workspace else {
+            continue;
+        };
+        // The first workspace above is the only candidate, the package is a root if not its member
+        let is_member = expand_members(
+            dir,
+            &workspace.members,
+            &workspace.exclude,
+            &mut Workspace::default(),
+        )
+        .iter()
+        .any(|m| m.canonicalize().is_ok_and(|m| m == canonical));
+        log::debug!("Found workspace at {dir:?} (member: {is_member}).");
+        return Ok(if is_member {
+            dir.to_path_buf()
+        } else {
+            path.to_path_buf()
+        });
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Get directories of workspace members, relative to the workspace root at `root`.
+///
+/// Members may be globs (e.g. `crates/*` or `crates/**`), in which case only directories with
+/// a manifest that aren't `exclude`d are kept.
+fn expand_members(
+    root: &Path,
+    members: &[String],
+    exclude: &[String],
+    workspace: &mut Workspace,
+) -> Vec<PathBuf> {
+    let exclude = exclude
+        .iter()
+        .map(|e| root.join(e))
+        .map(|e| e.canonicalize().unwrap_or(e))
+        .collect::<Vec<_>>();
+    let mut paths = vec![];
+    for member in members {
+        let pattern = root.join(member);
+        if !member.contains(['*', '?', '[']) {
+            paths.push(pattern);
+            continue;
+        }
+        let matches = match glob::glob(&pattern.to_string_lossy()) {
+            Ok(matches) => matches,
+            Err(e) => {
+                workspace.diagnostics.push(Diagnostic::new(
+                    &pattern,
+                    format!("invalid workspace member glob: {e}"),
+                ));
                 continue;
             }
-            log::debug!("Handling workspace: {member}.");
-            if let Some(member) = member.strip_suffix("/*") {
-                let path = path.join(member);
-                for dir in only_dir_names(&mut std::fs::read_dir(&path).unwrap()) {
-                    let path = path.join(dir);
-                    let manifest = new_complete_manifest_from_path(&path).unwrap();
-                    ret.append(&mut targets_from_manifest(&manifest, &path));
+        };
+        for path in matches {
+            let path = match path {
+                Ok(path) => path,
+                Err(e) => {
+                    workspace.diagnostics.push(Diagnostic::new(
+                        e.path(),
+                        format!("couldn't read workspace members: {}", e.error()),
+                    ));
+                    continue;
                 }
+            };
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if !path.join("Cargo.toml").is_file() {
+                log::debug!("Skipping {path:?}, it has no manifest.");
+            } else if exclude.iter().any(|e| canonical.starts_with(e)) {
+                log::debug!("Skipping {path:?}, it's excluded from the workspace.");
             } else {
-                let member_path = &path.join(member);
-                let manifest = new_complete_manifest_from_path(member_path).unwrap();
-                ret.append(&mut targets_from_manifest(&manifest, member_path));
+                paths.push(path);
             }
         }
     }
-    ret
+    paths
 }
 
-fn only_dir_names(dir: &mut std::fs::ReadDir) -> Vec<String> {
-    dir.flatten()
-        .filter_map(|e| {
-            e.file_type()
-                .map(|f| {
-                    if f.is_dir() {
-                        Some(e.file_name().to_string_lossy().to_string())
-                    } else {
-                        None
-                    }
-                })
-                .ok()
-                .flatten()
-        })
-        .collect::<Vec<_>>()
+/// Target matching a pattern, see [`match_targets`].
+#[derive(Debug, Clone, Copy, Serialize)]
+#[non_exhaustive]
+pub struct Match<'a> {
+    pub target: &'a Target,
+    /// How well the target matched, higher is better.
+    pub score: i64,
 }
 
-pub fn score_targets<'a>(
+/// Get targets matching `pattern`, from the best match to the worst one.
+///
+/// Targets of `preferred_package` are ranked above others matching equally well.
+pub fn match_targets<'a>(
     targets: &'a [Target],
     pattern: &str,
-    skim: &SkimMatcherV2,
-) -> Vec<&'a Target> {
+    preferred_package: Option<&str>,
+) -> Vec<Match<'a>> {
     log::debug!("Scoring targets with pattern: {pattern}.");
+    let skim = SkimMatcherV2::default();
     let mut ret = targets
         .iter()
-        .map(|target| (target, target.fuzzy_match(pattern, skim)))
+        .map(|target| (target, target.fuzzy_match(pattern, &skim)))
         .filter(|&(_target, score)| score > 0)
         .collect::<Vec<_>>();
 
     //TODO: maybe change this?
     ret.sort_unstable_by_key(|&(target, _score)| Reverse(target.to_string()));
-    ret.sort_by_key(|&(_, score)| score);
-    ret.iter().map(|&(t, _)| t).collect()
+    ret.sort_by_key(|&(target, score)| (score, Some(target.package()) == preferred_package));
+    ret.iter()
+        .rev()
+        .map(|&(target, score)| Match { target, score })
+        .collect()
 }
 
-pub fn new_complete_manifest_from_path(path: &Path) -> Result<Manifest, Box<dyn Error>> {
+/// Get up to `count` names of `targets` closest to `pattern` by edit distance, best first.
+pub fn near_misses(targets: &[Target], pattern: &str, count: usize) -> Vec<String> {
+    let pattern = pattern.to_lowercase();
+    let mut names = targets
+        .iter()
+        .map(|t| {
+            let name = t.name();
+            // Compare with the last segment of paths too, e.g. `parse` for `tests::parse`
+            let similarity = [name, name.rsplit("::").next().unwrap_or(name)]
+                .iter()
+                .map(|n| strsim::normalized_damerau_levenshtein(&n.to_lowercase(), &pattern))
+                .fold(0.0, f64::max);
+            (name, similarity)
+        })
+        .filter(|&(_, similarity)| similarity >= 0.5)
+        .collect::<Vec<_>>();
+    names.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut ret = Vec::<String>::new();
+    for (name, _) in names {
+        if !ret.iter().any(|n| n == name) {
+            ret.push(name.to_owned());
+        }
+    }
+    ret.truncate(count);
+    ret
+}
+
+pub(crate) fn new_complete_manifest_from_path(path: &Path) -> Result<Manifest, SelectError> {
     log::info!("Getting complete manifest from path: {path:?}");
     let path = path.join("Cargo.toml");
-    let mut manifest = Manifest::from_path(&path)?;
-    manifest.complete_from_path(&path)?;
+    let mut manifest = Manifest::from_path(&path).map_err(|e| manifest_error(path.clone(), e))?;
+    manifest
+        .complete_from_path(&path)
+        .map_err(|e| manifest_error(path, e))?;
     Ok(manifest)
 }
+
+/// Tell a missing manifest at `path` apart from a broken one.
+fn manifest_error(path: PathBuf, error: cargo_toml::Error) -> SelectError {
+    match error {
+        cargo_toml::Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            SelectError::ManifestNotFound { path }
+        }
+        e => SelectError::ManifestParse {
+            path,
+            message: e.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    fn package(name: &str) -> String {
+        format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n")
+    }
+
+    /// Workspace with members matched by globs, an excluded member, a directory without a
+    /// manifest and a package inside it that isn't a member.
+    fn workspace_dir(name: &str) -> PathBuf {
+        temp_dir(
+            name,
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"crates/**\", \"tools-*\", \"app\"]\nexclude = [\"crates/skip\"]\ndefault-members = [\"app\", \"tools-*\"]\n",
+                ),
+                ("app/Cargo.toml", &package("app")),
+                ("app/src/main.rs", "fn main() {}\n"),
+                ("crates/a/Cargo.toml", &package("a")),
+                ("crates/a/src/lib.rs", ""),
+                ("crates/nested/b/Cargo.toml", &package("b")),
+                ("crates/nested/b/src/lib.rs", ""),
+                ("crates/no-manifest/src/lib.rs", ""),
+                ("crates/skip/Cargo.toml", &package("skip")),
+                ("crates/skip/src/lib.rs", ""),
+                ("tools-x/Cargo.toml", &package("tools-x")),
+                ("tools-x/src/main.rs", "fn main() {}\n"),
+                ("tools.txt", ""),
+            ],
+        )
+    }
+
+    #[test]
+    fn expands_member_globs() {
+        let dir = workspace_dir("members");
+        let mut workspace = Workspace::default();
+        let members = expand_members(
+            &dir,
+            &[
+                "crates/**".to_owned(),
+                "tools-*".to_owned(),
+                "app".to_owned(),
+            ],
+            &["crates/skip".to_owned()],
+            &mut workspace,
+        );
+        let mut members = members
+            .iter()
+            .map(|m| m.strip_prefix(&dir).unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        members.sort();
+        assert_eq!(members, ["app", "crates/a", "crates/nested/b", "tools-x"]);
+        assert!(workspace.diagnostics.is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reads_workspace_from_manifests() {
+        let dir = workspace_dir("manifests");
+        let manifest = new_complete_manifest_from_path(&dir).unwrap();
+        let workspace = workspace_from_manifest(&manifest, &dir);
+        let mut packages = workspace
+            .packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.default_member))
+            .collect::<Vec<_>>();
+        packages.sort();
+        assert_eq!(
+            packages,
+            [("a", false), ("app", true), ("b", false), ("tools-x", true)]
+        );
+        assert!(workspace.diagnostics.is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn finds_workspace_root_of_members() {
+        let dir = workspace_dir("root");
+        for member in ["", "crates/a", "crates/nested/b", "tools-x"] {
+            assert_eq!(find_workspace_root(&dir.join(member)).unwrap(), dir);
+        }
+        // Excluded packages are the roots of their own workspace
+        let excluded = dir.join("crates/skip");
+        assert_eq!(find_workspace_root(&excluded).unwrap(), excluded);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+use std::process::Command;
+
+/// Quote `word` for POSIX shells, leaving it as is if it's safe.
+pub fn quote(word: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./=:,@+%^".contains(c);
+    if !word.is_empty() && word.chars().all(is_safe) {
+        word.to_owned()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// `command` as a shell command line: variables set for it, its program and arguments.
+pub fn command_line(command: &Command) -> String {
+    let env = command.get_envs().filter_map(|(key, value)| {
+        Some(format!(
+            "{}={}",
+            key.to_string_lossy(),
+            quote(&value?.to_string_lossy())
+        ))
+    });
+    let program = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| quote(&arg.to_string_lossy()));
+    env.chain(program).collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,806 @@
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, FnArg, ImplItem, Item, ItemFn, Meta, Token, TraitItem};
+
+use crate::doc_tests::{doc_tests, included_file, FoundDocTest};
+
+/// Attributes marking test functions that are recognised out of the box.
+pub const KNOWN_TEST_ATTRIBUTES: &[&str] = &[
+    "test",
+    "tokio::test",
+    "async_std::test",
+    "actix_rt::test",
+    "actix_web::test",
+    "test_log::test",
+    "sqlx::test",
+    "rstest",
+    "rstest::rstest",
+    "test_case",
+    "test_case::test_case",
+    "wasm_bindgen_test",
+    "wasm_bindgen_test::wasm_bindgen_test",
+];
+
+/// Test function found in a source file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoundTest {
+    /// Path of the function relative to the file's module, including inline modules.
+    pub path: Vec<String>,
+    pub line: usize,
+    pub column: usize,
+    /// Paths of the function's attributes, e.g. `test` or `should_panic`.
+    pub attributes: Vec<String>,
+    /// Whether the function is expanded into a module of test cases (e.g. by `rstest`).
+    pub parametrised: bool,
+    /// Names of the generated test cases, relative to the function.
+    pub cases: Vec<String>,
+    /// Whether the function is marked with `#[ignore]`.
+    pub ignored: bool,
+    /// Reason given with `#[ignore = "reason"]`.
+    pub ignore_reason: Option<String>,
+    pub should_panic: bool,
+}
+
+fn attribute_path(attr: &Attribute) -> String {
+    attr.path()
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Get whether `#[ignore]` is among `attrs`, along with its reason if there is one.
+fn ignore_attribute(attrs: &[Attribute]) -> (bool, Option<String>) {
+    let Some(attr) = attrs.iter().find(|a| a.path().is_ident("ignore")) else {
+        return (false, None);
+    };
+    let reason = match &attr.meta {
+        Meta::NameValue(nv) => match &nv.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(reason),
+                ..
+            }) => Some(reason.value()),
+            _ => None,
+        },
+        _ => None,
+    };
+    (true, reason)
+}
+
+fn is_test_attribute(path: &str, extra_attributes: &[String]) -> bool {
+    KNOWN_TEST_ATTRIBUTES.contains(&path) || extra_attributes.iter().any(|a| a == path)
+}
+
+/// Escape a test case description into an identifier, the same way `test-case` does.
+fn escape_test_name(description: &str) -> String {
+    if description.is_empty() {
+        return "_empty".to_owned();
+    }
+    let mut last_underscore = false;
+    let mut name = description
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() {
+                last_underscore = false;
+                Some(c.to_ascii_lowercase())
+            } else if !last_underscore {
+                last_underscore = true;
+                Some('_')
+            } else {
+                None
+            }
+        })
+        .collect::<String>();
+    if !name.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Get the name of the test generated from the `number`th `#[test_case(...)]` attribute.
+///
+/// That is `test_case_N_` followed by the escaped description if there is one
+/// (`#[test_case(1, 2 ; "description")]`), otherwise the escaped arguments and expected result
+/// (`#[test_case(1 => 2)]`). `None` if the name can't be worked out from the tokens.
+fn test_case_name(attr: &Attribute, number: usize) -> Option<String> {
+    let Meta::List(list) = &attr.meta else {
+        return None;
+    };
+    let tokens = list.tokens.clone().into_iter().collect::<Vec<_>>();
+    let mut parts = tokens.split(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == ';'));
+    let arguments = parts.next().unwrap_or_default();
+    if let Some(description) = parts.next() {
+        let description = syn::parse2::<syn::LitStr>(description.iter().cloned().collect()).ok()?;
+        return Some(numbered_test_case_name(
+            number,
+            escape_test_name(&description.value()),
+        ));
+    }
+    // Split the arguments from the expected result on `=>`
+    let arrow = arguments.windows(2).position(|w| {
+        matches!(&w[0], TokenTree::Punct(p) if p.as_char() == '=' && p.spacing() == Spacing::Joint)
+            && matches!(&w[1], TokenTree::Punct(p) if p.as_char() == '>')
+    });
+    let (arguments, expected) = match arrow {
+        Some(i) => (&arguments[..i], Some(&arguments[i + 2..])),
+        None => (arguments, None),
+    };
+    let arguments = Punctuated::<Expr, Token![,]>::parse_separated_nonempty
+        .parse2(arguments.iter().cloned().collect())
+        .ok()?;
+    let mut description = String::new();
+    for argument in arguments {
+        description.push_str(&argument.to_token_stream().to_string());
+        description.push('_');
+    }
+    description.push_str("expects");
+    if let Some(expected) = expected {
+        description.push(' ');
+        description.push_str(&expected_description(expected)?);
+    }
+    Some(numbered_test_case_name(
+        number,
+        escape_test_name(&description),
+    ))
+}
+
+/// Describe the expected result of a test case (the tokens after `=>`) like `test-case` does
+/// in test names, e.g. `matching Some (_) ` for `matches Some(_)`.
+///
+/// `None` for complex assertions (`it ...`/`is ...`), which are described by their parsed form.
+fn expected_description(mut tokens: &[TokenTree]) -> Option<String> {
+    let text = |tokens: &[TokenTree]| tokens.iter().cloned().collect::<TokenStream>().to_string();
+    let mut description = String::new();
+    // `ignore` and `inconclusive` modifiers, with an optional reason in brackets
+    let mut reasons = vec![];
+    while let [TokenTree::Ident(modifier), rest @ ..] = tokens {
+        if modifier != "ignore" && modifier != "inconclusive" {
+            break;
+        }
+        let (reason, rest) = match rest {
+            [TokenTree::Group(g), rest @ ..] if g.delimiter() == Delimiter::Bracket => {
+                (Some(g.stream().to_string()), rest)
+            }
+            _ => (None, rest),
+        };
+        if !reasons.contains(&reason) {
+            description.push_str("inconclusive");
+            reasons.push(reason);
+        }
+        tokens = rest;
+    }
+    let result = match tokens {
+        [TokenTree::Ident(keyword), rest @ ..] if keyword == "matches" => {
+            let guard = rest
+                .iter()
+                .position(|t| matches!(t, TokenTree::Ident(i) if i == "if"));
+            let (pattern, guard) = match guard {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => (rest, &[][..]),
+            };
+            format!("matching {} {}", text(pattern), text(guard))
+        }
+        [TokenTree::Ident(keyword), rest @ ..] if keyword == "panics" => {
+            let message = (!rest.is_empty()).then(|| text(rest));
+            format!("panicking {message:?}")
+        }
+        [TokenTree::Ident(keyword), rest @ ..] if keyword == "with" => {
+            format!("with {}", text(rest))
+        }
+        [TokenTree::Ident(keyword), rest @ ..] if keyword == "using" => {
+            format!("use {}", text(rest))
+        }
+        [TokenTree::Ident(keyword), ..] if keyword == "it" || keyword == "is" => return None,
+        [] if !reasons.is_empty() => "empty".to_owned(),
+        tokens => text(tokens),
+    };
+    description.push_str(&result);
+    Some(description)
+}
+
+fn numbered_test_case_name(number: usize, name: String) -> String {
+    let separator = if name.starts_with('_') { "" } else { "_" };
+    format!("test_case_{number}{separator}{name}")
+}
+
+/// Get names of the cases `rstest` generates from `#[case]` attributes.
+fn rstest_cases(attrs: &[Attribute]) -> Vec<String> {
+    let descriptions = attrs
+        .iter()
+        .filter(|a| a.path().segments.first().is_some_and(|s| s.ident == "case"))
+        .map(|a| a.path().segments.iter().nth(1).map(|s| s.ident.to_string()))
+        .collect::<Vec<_>>();
+    let width = descriptions.len().to_string().len();
+    descriptions
+        .into_iter()
+        .enumerate()
+        .map(|(i, description)| {
+            let description = description.map(|d| format!("_{d}")).unwrap_or_default();
+            format!("case_{:0width$}{description}", i + 1)
+        })
+        .collect()
+}
+
+/// Get cases of a parametrised test, or `None` if `function` is a plain test.
+fn parametrised_cases(function: &ItemFn, attributes: &[String]) -> Option<Vec<String>> {
+    if attributes
+        .iter()
+        .any(|a| a == "test_case" || a == "test_case::test_case")
+    {
+        if attributes
+            .iter()
+            .any(|a| a == "test_matrix" || a == "test_case::test_matrix")
+        {
+            // Cases of a matrix are numbered along with the other ones, only the whole group is
+            // selectable
+            return Some(vec![]);
+        }
+        let cases = function
+            .attrs
+            .iter()
+            .filter(|a| {
+                matches!(
+                    attribute_path(a).as_str(),
+                    "test_case" | "test_case::test_case"
+                )
+            })
+            .enumerate()
+            .filter_map(|(i, a)| test_case_name(a, i + 1))
+            .collect();
+        return Some(cases);
+    }
+    if attributes
+        .iter()
+        .any(|a| a == "rstest" || a == "rstest::rstest")
+    {
+        let cases = rstest_cases(&function.attrs);
+        let has_values = function.sig.inputs.iter().any(|input| match input {
+            FnArg::Typed(arg) => arg.attrs.iter().any(|a| a.path().is_ident("values")),
+            FnArg::Receiver(_) => false,
+        });
+        if has_values {
+            // Combinations of `#[values]` are nested modules, only the whole group is selectable
+            return Some(vec![]);
+        }
+        if !cases.is_empty() {
+            return Some(cases);
+        }
+    }
+    None
+}
+
+/// Module declared with `mod name;`, whose contents are in another file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoundModule {
+    /// Path of the module relative to the file's module, including inline modules.
+    pub path: Vec<String>,
+    /// Directories of the inline modules the declaration is in, i.e. their names or `#[path]`s.
+    pub dirs: Vec<String>,
+    /// Value of the declaration's `#[path = "..."]` attribute.
+    pub file: Option<String>,
+}
+
+/// Tests, doc tests and module declarations found in a source file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedSource {
+    pub tests: Vec<FoundTest>,
+    pub doc_tests: Vec<FoundDocTest>,
+    pub modules: Vec<FoundModule>,
+    /// Files included in documentation with `include_str!`.
+    pub includes: Vec<PathBuf>,
+}
+
+/// Get the value of the `#[path = "..."]` attribute among `attrs`.
+fn path_attribute(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("path"))
+        .find_map(|a| match &a.meta {
+            Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(path),
+                        ..
+                    }),
+                ..
+            }) => Some(path.value()),
+            _ => None,
+        })
+}
+
+/// Get the name rustdoc gives to the type of an `impl` block, e.g. `Wrap<T>`.
+fn impl_type_name(ty: &syn::Type) -> String {
+    ty.to_token_stream().to_string().replace(' ', "")
+}
+
+fn item_path(modules: &[String], names: &[String]) -> Vec<String> {
+    modules.iter().chain(names).cloned().collect()
+}
+
+fn items_from_items(
+    items: &[Item],
+    extra_attributes: &[String],
+    dir: &Path,
+    modules: &mut Vec<String>,
+    module_dirs: &mut Vec<String>,
+    found: &mut ParsedSource,
+) {
+    for item in items {
+        let mut docs = |attrs: &[Attribute], names: &[String]| {
+            found
+                .includes
+                .extend(attrs.iter().filter_map(|a| included_file(a, dir)));
+            found
+                .doc_tests
+                .extend(doc_tests(attrs, &item_path(modules, names), dir));
+        };
+        match item {
+            Item::Fn(function) => {
+                let ident = function.sig.ident.to_string();
+                docs(&function.attrs, &[ident]);
+                if let Some(test) = found_test(function, extra_attributes, modules) {
+                    found.tests.push(test);
+                }
+            }
+            Item::Struct(item) => {
+                let ident = item.ident.to_string();
+                docs(&item.attrs, std::slice::from_ref(&ident));
+                for field in &item.fields {
+                    if let Some(field_ident) = &field.ident {
+                        docs(&field.attrs, &[ident.clone(), field_ident.to_string()]);
+                    }
+                }
+            }
+            Item::Enum(item) => {
+                let ident = item.ident.to_string();
+                docs(&item.attrs, std::slice::from_ref(&ident));
+                for variant in &item.variants {
+                    docs(&variant.attrs, &[ident.clone(), variant.ident.to_string()]);
+                }
+            }
+            Item::Union(item) => docs(&item.attrs, &[item.ident.to_string()]),
+            Item::Type(item) => docs(&item.attrs, &[item.ident.to_string()]),
+            Item::Const(item) => docs(&item.attrs, &[item.ident.to_string()]),
+            Item::Static(item) => docs(&item.attrs, &[item.ident.to_string()]),
+            Item::Macro(item) => {
+                if let Some(ident) = &item.ident {
+                    docs(&item.attrs, &[ident.to_string()]);
+                }
+            }
+            Item::Trait(item) => {
+                let ident = item.ident.to_string();
+                docs(&item.attrs, std::slice::from_ref(&ident));
+                for trait_item in &item.items {
+                    let (attrs, name) = match trait_item {
+                        TraitItem::Fn(f) => (&f.attrs, &f.sig.ident),
+                        TraitItem::Const(c) => (&c.attrs, &c.ident),
+                        TraitItem::Type(t) => (&t.attrs, &t.ident),
+                        _ => continue,
+                    };
+                    docs(attrs, &[ident.clone(), name.to_string()]);
+                }
+            }
+            Item::Impl(item) => {
+                let ty = impl_type_name(&item.self_ty);
+                for impl_item in &item.items {
+                    let (attrs, name) = match impl_item {
+                        ImplItem::Fn(f) => (&f.attrs, &f.sig.ident),
+                        ImplItem::Const(c) => (&c.attrs, &c.ident),
+                        ImplItem::Type(t) => (&t.attrs, &t.ident),
+                        _ => continue,
+                    };
+                    docs(attrs, &[ty.clone(), name.to_string()]);
+                }
+            }
+            Item::Mod(module) => {
+                let ident = module.ident.to_string();
+                docs(&module.attrs, std::slice::from_ref(&ident));
+                let file = path_attribute(&module.attrs);
+                match &module.content {
+                    Some((_, items)) => {
+                        modules.push(ident.clone());
+                        module_dirs.push(file.unwrap_or(ident));
+                        items_from_items(items, extra_attributes, dir, modules, module_dirs, found);
+                        modules.pop();
+                        module_dirs.pop();
+                    }
+                    None => found.modules.push(FoundModule {
+                        path: item_path(modules, &[ident]),
+                        dirs: module_dirs.clone(),
+                        file,
+                    }),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Get the test `function` is, if it's marked with a test attribute.
+fn found_test(
+    function: &ItemFn,
+    extra_attributes: &[String],
+    modules: &[String],
+) -> Option<FoundTest> {
+    let attributes = function
+        .attrs
+        .iter()
+        .map(attribute_path)
+        .collect::<Vec<_>>();
+    if !attributes
+        .iter()
+        .any(|a| is_test_attribute(a, extra_attributes))
+    {
+        return None;
+    }
+    let ident = &function.sig.ident;
+    let start = ident.span().start();
+    log::trace!("Found test: {ident}");
+    let cases = parametrised_cases(function, &attributes);
+    let (ignored, ignore_reason) = ignore_attribute(&function.attrs);
+    Some(FoundTest {
+        path: item_path(modules, &[ident.to_string()]),
+        line: start.line,
+        column: start.column + 1,
+        should_panic: attributes.iter().any(|a| a == "should_panic"),
+        attributes,
+        parametrised: cases.is_some(),
+        cases: cases.unwrap_or_default(),
+        ignored,
+        ignore_reason,
+    })
+}
+
+/// Get test functions, doc tests and module declarations from Rust source code.
+///
+/// Functions are recognised as tests by [`KNOWN_TEST_ATTRIBUTES`] and `extra_attributes`.
+/// `dir` is the directory of the source file, used to resolve included documentation.
+pub fn parse_source(
+    source: &str,
+    dir: &Path,
+    extra_attributes: &[String],
+) -> syn::Result<ParsedSource> {
+    let file = syn::parse_file(source)?;
+    let mut found = ParsedSource {
+        doc_tests: doc_tests(&file.attrs, &[], dir),
+        includes: file
+            .attrs
+            .iter()
+            .filter_map(|a| included_file(a, dir))
+            .collect(),
+        ..Default::default()
+    };
+    items_from_items(
+        &file.items,
+        extra_attributes,
+        dir,
+        &mut vec![],
+        &mut vec![],
+        &mut found,
+    );
+    Ok(found)
+}
+
+/// Strip visibility modifiers (`pub`, `pub(crate)`, ...) from the start of `line`.
+fn strip_visibility(line: &str) -> &str {
+    if let Some(rest) = line.strip_prefix("pub(") {
+        rest.find(')')
+            .map(|i| rest[i + 1..].trim_start())
+            .unwrap_or(line)
+    } else {
+        line.strip_prefix("pub ").unwrap_or(line).trim_start()
+    }
+}
+
+/// Get the name of the module declared on `line` and whether it's inline, e.g. `tests` for
+/// `mod tests {`.
+fn module_name(line: &str) -> Option<(&str, bool)> {
+    let rest = strip_visibility(line).strip_prefix("mod ")?.trim_start();
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    match rest[end..].trim_start().chars().next() {
+        Some('{') => Some((&rest[..end], true)),
+        Some(';') => Some((&rest[..end], false)),
+        _ => None,
+    }
+}
+
+/// Get module declarations from Rust source code by scanning it line by line, for sources
+/// without tests that aren't worth parsing.
+pub fn scan_modules(source: &str) -> Vec<FoundModule> {
+    scan_source(source, &[]).modules
+}
+
+/// Get test functions and module declarations from Rust source code by scanning it line by
+/// line.
+///
+/// Used as a fallback for sources `syn` can't parse.
+pub fn scan_source(source: &str, extra_attributes: &[String]) -> ParsedSource {
+    let mut found = ParsedSource::default();
+    // Value of the last `#[path = "..."]` attribute
+    let mut path_attribute = None;
+    let mut find_test_function = false;
+    // Attributes seen since the last item
+    let mut attributes: Vec<String> = vec![];
+    let mut ignore_reason = None;
+    // Inline modules we are in, along with the brace depth of their bodies
+    let mut modules: Vec<(String, usize)> = vec![];
+    let mut depth = 0;
+    for (line_idx, line) in source.lines().enumerate() {
+        let line = line.trim();
+        let code = line.split("//").next().unwrap_or_default();
+        match module_name(code) {
+            Some((name, true)) => modules.push((name.to_owned(), depth + 1)),
+            Some((name, false)) => found.modules.push(FoundModule {
+                path: modules
+                    .iter()
+                    .map(|(m, _)| m.clone())
+                    .chain([name.to_owned()])
+                    .collect(),
+                dirs: modules.iter().map(|(m, _)| m.clone()).collect(),
+                file: path_attribute.take(),
+            }),
+            None => {}
+        }
+        depth += code.matches('{').count();
+        depth = depth.saturating_sub(code.matches('}').count());
+        while modules.last().is_some_and(|&(_, d)| d > depth) {
+            modules.pop();
+        }
+
+        let attribute = line.strip_prefix("#[").map(|a| {
+            a.split(['(', ']', '='])
+                .next()
+                .unwrap_or_default()
+                .replace(' ', "")
+        });
+        if let Some(attribute) = attribute {
+            find_test_function |= is_test_attribute(&attribute, extra_attributes);
+            if attribute == "path" {
+                path_attribute = line
+                    .split_once('=')
+                    .map(|(_, p)| p.trim().trim_end_matches(']').trim().trim_matches('"'))
+                    .map(str::to_owned);
+            } else if attribute == "ignore" {
+                // `#[ignore = "reason"]`
+                ignore_reason = line
+                    .split_once('=')
+                    .map(|(_, r)| r.trim().trim_end_matches(']').trim().trim_matches('"'))
+                    .map(str::to_owned);
+            }
+            attributes.push(attribute);
+        } else if !find_test_function {
+            attributes.clear();
+            ignore_reason = None;
+            path_attribute = None;
+        } else {
+            let item = strip_visibility(line);
+            let item = item.strip_prefix("async ").unwrap_or(item);
+            let item = item.strip_prefix("unsafe ").unwrap_or(item);
+            if let Some(line) = item.strip_prefix("fn") {
+                // get just the name of the function
+                let i = line
+                    .find('(')
+                    .unwrap_or_else(|| line.len().saturating_sub(1));
+                let name = line[..i].trim();
+                find_test_function = false;
+                if name.is_empty() {
+                    continue;
+                }
+
+                log::trace!("Found test: {name}");
+
+                found.tests.push(FoundTest {
+                    path: modules
+                        .iter()
+                        .map(|(m, _)| m.clone())
+                        .chain([name.to_owned()])
+                        .collect(),
+                    line: line_idx + 1,
+                    column: 1,
+                    ignored: attributes.iter().any(|a| a == "ignore"),
+                    ignore_reason: ignore_reason.take(),
+                    should_panic: attributes.iter().any(|a| a == "should_panic"),
+                    attributes: std::mem::take(&mut attributes),
+                    parametrised: false,
+                    cases: vec![],
+                });
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the cases found in the test function `name` of `source`.
+    fn cases(source: &str, name: &str) -> Vec<String> {
+        let found = parse_source(source, Path::new("."), &[]).expect("Source parses.");
+        let test = found
+            .tests
+            .into_iter()
+            .find(|t| t.path.last().map(String::as_str) == Some(name))
+            .expect("Test is found.");
+        assert!(test.parametrised);
+        test.cases
+    }
+
+    // Expected names are the ones listed by `cargo test -- --list` with test-case 3.4
+
+    #[test]
+    fn test_case_descriptions() {
+        let source = r#"
+            #[test_case(1, 2 ; "Adds two Numbers")]
+            #[test_case(3, 4 ; "")]
+            #[test_case(r"raw\n" ; r"raw description")]
+            #[test_case("quoted" ; "It's \"quoted\"")]
+            fn described(a: i32, b: i32) {}
+        "#;
+        assert_eq!(
+            cases(source, "described"),
+            [
+                "test_case_1_adds_two_numbers",
+                "test_case_2_empty",
+                "test_case_3_raw_description",
+                "test_case_4_it_s_quoted_",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_arguments() {
+        let source = r#"
+            #[test_case(1, 2)]
+            #[test_case(std::collections::HashMap::<u8, u8>::new().len(), 7)]
+            fn arguments(a: usize, b: i32) {}
+        "#;
+        assert_eq!(
+            cases(source, "arguments"),
+            [
+                "test_case_1_1_2_expects",
+                "test_case_2_std_collections_hashmap_u8_u8_new_len_7_expects",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_expected_results() {
+        let source = r#"
+            #[test_case(2, 3 => 5)]
+            #[test_case(0, 0 => panics "zero")]
+            #[test_case(-1, 2 => ignore 2)]
+            #[test_case(1, 1 => with |_x: i32| ())]
+            #[test_case(1, 1 => using check)]
+            #[test_case(1, 1 => matches x if x > 0)]
+            #[test_case(vec![1, 2].len() as i32, 3 => inconclusive["why"] 3)]
+            #[test_case(Some(1) => matches Some(_))]
+            #[test_case(None => ignore)]
+            fn expected(a: i32, b: i32) -> i32 {}
+        "#;
+        assert_eq!(
+            cases(source, "expected"),
+            [
+                "test_case_1_2_3_expects_5",
+                "test_case_2_0_0_expects_panicking_some_zero_",
+                "test_case_3_1_2_expects_inconclusive2",
+                "test_case_4_1_1_expects_with_x_i32_",
+                "test_case_5_1_1_expects_use_check",
+                "test_case_6_1_1_expects_matching_x_x_0",
+                "test_case_7_vec_1_2_len_as_i32_3_expects_inconclusive3",
+                "test_case_8_some_1_expects_matching_some_",
+                "test_case_9_none_expects_inconclusiveempty",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_complex_assertions_are_skipped() {
+        let source = r#"
+            #[test_case(1 => is greater_than 0)]
+            #[test_case(2 => 2)]
+            fn complex(a: i32) -> i32 {}
+        "#;
+        assert_eq!(cases(source, "complex"), ["test_case_2_2_expects_2"]);
+    }
+
+    #[test]
+    fn rstest_case_numbering() {
+        let source = r#"
+            #[rstest]
+            #[case(1)]
+            #[case(2)]
+            #[case(3)]
+            #[case(4)]
+            #[case::fifth(5)]
+            #[case(6)]
+            #[case(7)]
+            #[case(8)]
+            #[case(9)]
+            #[case::tenth(10)]
+            fn numbered(#[case] x: i32) {}
+        "#;
+        assert_eq!(
+            cases(source, "numbered"),
+            [
+                "case_01",
+                "case_02",
+                "case_03",
+                "case_04",
+                "case_05_fifth",
+                "case_06",
+                "case_07",
+                "case_08",
+                "case_09",
+                "case_10_tenth",
+            ]
+        );
+    }
+
+    #[test]
+    fn rstest_few_cases_are_not_padded() {
+        let source = r#"
+            #[rstest]
+            #[case::first(1)]
+            #[case(2)]
+            fn few(#[case] x: i32) {}
+        "#;
+        assert_eq!(cases(source, "few"), ["case_1_first", "case_2"]);
+    }
+
+    // Expected items are the ones named by `cargo test --doc -- --list`
+
+    #[test]
+    fn doc_test_items_in_impl_and_trait_blocks() {
+        let source = r#"
+pub struct Wrap<T>(pub T);
+impl<T> Wrap<T> {
+    /// ```
+    /// assert!(true);
+    /// ```
+    pub fn get(&self) {}
+}
+pub trait Shape {
+    /// ```
+    /// assert!(true);
+    /// ```
+    fn area(&self);
+}
+impl Shape for Wrap<u8> {
+    /// ```
+    /// assert!(true);
+    /// ```
+    fn area(&self) {}
+}
+mod inner {
+    /// ```
+    /// assert!(true);
+    /// ```
+    pub fn run() {}
+}
+"#;
+        let found = parse_source(source, Path::new("."), &[]).expect("Source parses.");
+        let items = found
+            .doc_tests
+            .iter()
+            .map(|t| (t.item.join("::"), t.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                ("Wrap<T>::get".to_owned(), 4),
+                ("Shape::area".to_owned(), 10),
+                ("Wrap<u8>::area".to_owned(), 16),
+                ("inner::run".to_owned(), 22)
+            ]
+        );
+    }
+}
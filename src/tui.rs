@@ -1,12 +1,13 @@
-use crate::select::{score_targets, Target};
+use cargo_select::{match_targets, near_misses, SelectError, Target};
 
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use fuzzy_matcher::skim::SkimMatcherV2;
-use std::{error::Error, io};
+
+use crate::exit::CliError;
+use std::io::{self, IsTerminal, Write};
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout},
@@ -18,40 +19,56 @@ use tui::{
 pub struct Tui;
 
 impl Tui {
-    pub fn launch(targets: &[Target]) -> Result<&Target, Box<dyn Error>> {
+    /// Let the user pick one of `targets`, ranking ones from `preferred_package` first.
+    pub fn launch<'a>(
+        targets: &'a [Target],
+        preferred_package: Option<&str>,
+    ) -> Result<&'a Target, CliError> {
         // setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+        enable_raw_mode().map_err(CliError::terminal)?;
+        // Draw on stderr if stdout is captured, e.g. with `$(cargo select --print-command)`
+        let mut output: Box<dyn Write> = if io::stdout().is_terminal() {
+            Box::new(io::stdout())
+        } else {
+            Box::new(io::stderr())
+        };
+        execute!(output, EnterAlternateScreen, EnableMouseCapture).map_err(CliError::terminal)?;
+        let backend = CrosstermBackend::new(output);
+        let mut terminal = Terminal::new(backend).map_err(CliError::terminal)?;
 
-        let res = Tui::main_loop(&mut terminal, targets);
+        let res = Tui::main_loop(&mut terminal, targets, preferred_package);
 
         // restore terminal
-        disable_raw_mode()?;
+        disable_raw_mode().map_err(CliError::terminal)?;
         execute!(
             terminal.backend_mut(),
             LeaveAlternateScreen,
             DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
+        )
+        .map_err(CliError::terminal)?;
+        terminal.show_cursor().map_err(CliError::terminal)?;
         res
     }
 
     fn main_loop<'a, B: Backend>(
         terminal: &mut Terminal<B>,
         targets: &'a [Target],
-    ) -> Result<&'a Target, Box<dyn Error>> {
+        preferred_package: Option<&str>,
+    ) -> Result<&'a Target, CliError> {
         let mut pattern = String::new();
         let mut list_state = ListState::default();
         let mut selected_idx = 0;
-        let skim = SkimMatcherV2::default();
+        let candidates = targets;
         loop {
             let terminal_height: usize = terminal.size().unwrap().height.into();
 
-            let targets = if !pattern.is_empty() {
-                score_targets(targets, &pattern, &skim)
+            let targets: Vec<&Target> = if !pattern.is_empty() {
+                // The best matches go last, closest to the prompt
+                match_targets(targets, &pattern, preferred_package)
+                    .into_iter()
+                    .rev()
+                    .map(|m| m.target)
+                    .collect()
             } else {
                 targets.iter().collect()
             };
@@ -65,14 +82,16 @@ impl Tui {
             selected_idx = selected_idx.min(targets.len()).max(1);
             let transformed_idx = targets.len().saturating_sub(selected_idx);
             list_state.select(Some(transformed_idx));
-            terminal.draw(|f| Tui::ui(f, &targets, &pattern, &mut list_state))?;
+            terminal
+                .draw(|f| Tui::ui(f, &targets, &pattern, &mut list_state))
+                .map_err(CliError::terminal)?;
 
-            if let Event::Key(key) = crossterm::event::read()? {
+            if let Event::Key(key) = crossterm::event::read().map_err(CliError::terminal)? {
                 if (matches!(key.code, KeyCode::Char('c'))
                     && key.modifiers.contains(KeyModifiers::CONTROL))
                     || matches!(key.code, KeyCode::Esc)
                 {
-                    return Err("User interrupt.".into());
+                    return Err(CliError::Aborted);
                 }
                 if matches!(key.code, KeyCode::Char('w'))
                     && key.modifiers.contains(KeyModifiers::CONTROL)
@@ -93,7 +112,11 @@ impl Tui {
                     KeyCode::Down => selected_idx = selected_idx.saturating_sub(1),
                     KeyCode::Enter => {
                         if targets.is_empty() {
-                            return Err("No targets matched!".into());
+                            return Err(SelectError::NoTargetsMatched {
+                                near_misses: near_misses(candidates, &pattern, 3),
+                                pattern,
+                            }
+                            .into());
                         } else {
                             return Ok(targets[transformed_idx.min(targets.len() - 1)]);
                         }